@@ -26,6 +26,8 @@ fn default_keymap() -> HashMap<Code, GameInputEventKey> {
     map.insert(Code::ControlRight, GameInputEventKey::RotateRight);
     map.insert(Code::ArrowUp, GameInputEventKey::RotateRight);
 
+    map.insert(Code::KeyA, GameInputEventKey::Rotate180);
+
     map.insert(Code::ArrowLeft, GameInputEventKey::MoveLeft);
     map.insert(Code::ArrowRight, GameInputEventKey::MoveRight);
 
@@ -46,5 +48,9 @@ fn default_keymap() -> HashMap<Code, GameInputEventKey> {
     map.insert(Code::Minus, GameInputEventKey::MenuZoomIn);
     map.insert(Code::Equal, GameInputEventKey::MenuZoomOut);
 
+    map.insert(Code::KeyU, GameInputEventKey::PracticeUndo);
+    map.insert(Code::KeyY, GameInputEventKey::PracticeRedo);
+    map.insert(Code::KeyR, GameInputEventKey::PracticeRestart);
+
     map
 }
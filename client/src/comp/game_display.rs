@@ -69,6 +69,7 @@ pub fn YouDied(
             Some(GameOverReason::Knockout) => "K.O.",
             Some(GameOverReason::Disconnect) => "DISCONNECT",
             Some(GameOverReason::Abandon) => "ABANDON",
+            Some(GameOverReason::Complete) => "COMPLETE",
         }
         .to_string()
     });
@@ -80,6 +81,7 @@ pub fn YouDied(
             Some(GameOverReason::Knockout) => "red",
             Some(GameOverReason::Disconnect) => "orange",
             Some(GameOverReason::Abandon) => "purple",
+            Some(GameOverReason::Complete) => "gold",
         }
         .to_string()
     });
@@ -440,12 +442,16 @@ fn GameStateInfo(game_state: ReadSignal<GameState>) -> Element {
             ",
             div { "Score: {state.score}" }
             div { "Lines: {state.total_lines}" }
+            div { "Level: {state.level}" }
             div { "Moves: {state.total_moves}" }
             div { "Combo: {state.combo_counter}" }
             div { "Time: {state.current_time_string()}" }
             div { "Lines Sent: {state.total_garbage_sent}"}
             div { "Lines Recv: {state.garbage_recv}"}
             div { "Lines Applied: {state.garbage_applied}"}
+            div { "Lines Cancelled: {state.garbage_cancelled}"}
+            div { "PPS: {state.stats.pps():.2}" }
+            div { "APM: {state.stats.apm():.1}" }
 
 
             // Show B2B and T-spin indicators if active
@@ -461,10 +467,21 @@ fn GameStateInfo(game_state: ReadSignal<GameState>) -> Element {
                 }
             }
 
-            // Show garbage info if any
-            if state.garbage_recv > 0 {
+            if state.is_t_mini_spin {
+                div { style: "color: #ff69b4;",
+                    "T-Spin Mini!"
+                }
+            }
+
+            // Incoming garbage meter, one block per queued line
+            if state.pending_garbage() > 0 {
                 div { style: "color: #ff4444;", // Red color for garbage
-                    "Incoming: {state.garbage_recv - state.garbage_applied}"
+                    "Incoming: {state.pending_garbage()}"
+                }
+                for entry in state.garbage_queue.entries() {
+                    div {
+                        style: "background: #ff4444; width: 1em; margin-bottom: 2px; height: {entry.lines}em;",
+                    }
                 }
             }
         }
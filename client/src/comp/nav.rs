@@ -24,6 +24,9 @@ pub fn Nav() -> Element {
                 li {
                     Link { to: Route::PlaySingleplayerPage { }, small { "singleplayer" } }
                 }
+                li {
+                    Link { to: Route::PlayPracticePage { }, small { "practice" } }
+                }
                 // li {
                 //     Link { to: Route::IAmARobotSingleplayer { }, small { "robot" } }
                 // }
@@ -61,6 +61,140 @@ pub fn GameInputSettings() -> Element {
     rsx! {
         InitialRepeatDelaySlider{}
         AfterRepeatDelaySlider {}
+        DasCutDelaySlider {}
+        SoftDropFactorSlider {}
+        InitialInputCheckboxes {}
+        HandlingCheckboxes {}
+    }
+}
+
+#[component]
+fn DasCutDelaySlider() -> Element {
+    let old_settings = use_game_settings();
+    let old_init = old_settings.input.das_cut_delay.as_millis() as u16;
+
+    let label_initial = use_signal(|| "DAS Cut Delay (ms)".to_string());
+    let slider_das_cut = use_signal(|| old_init);
+    use_effect(move || {
+        let init = *slider_das_cut.read();
+        let init = init.clamp(0, 333);
+        if init != old_init {
+            let mut new_settings = old_settings;
+            new_settings.input.das_cut_delay = Duration::from_millis(init as u64);
+            set_game_settings(new_settings);
+        }
+    });
+    rsx! {
+        h5 {
+            "DAS Cut Delay"
+        }
+        Slider {
+            label: label_initial,
+            m: slider_das_cut,
+            default_value: 0,
+            min: 0,
+            max: 333
+        }
+    }
+}
+
+#[component]
+fn SoftDropFactorSlider() -> Element {
+    let old_settings = use_game_settings();
+    let old_init = old_settings.input.soft_drop_factor;
+
+    let label_initial =
+        use_signal(|| "Soft Drop Factor (0 = repeat delays)".to_string());
+    let slider_factor = use_signal(|| old_init);
+    use_effect(move || {
+        let init = *slider_factor.read();
+        let init = init.clamp(0, 100);
+        if init != old_init {
+            let mut new_settings = old_settings;
+            new_settings.input.soft_drop_factor = init;
+            set_game_settings(new_settings);
+        }
+    });
+    rsx! {
+        h5 {
+            "Soft Drop Factor"
+        }
+        Slider {
+            label: label_initial,
+            m: slider_factor,
+            default_value: 0,
+            min: 0,
+            max: 100
+        }
+    }
+}
+
+#[component]
+fn HandlingCheckboxes() -> Element {
+    let old_settings = use_game_settings();
+    rsx! {
+        h5 {
+            "Soft Drop / Hard Drop"
+        }
+        label {
+            input {
+                type: "checkbox",
+                checked: old_settings.input.instant_soft_drop,
+                onchange: move |e| {
+                    let mut new_settings = old_settings;
+                    new_settings.input.instant_soft_drop = e.checked();
+                    set_game_settings(new_settings);
+                }
+            }
+            "Instant Soft Drop"
+        }
+        label {
+            input {
+                type: "checkbox",
+                checked: old_settings.input.prevent_accidental_hard_drop,
+                onchange: move |e| {
+                    let mut new_settings = old_settings;
+                    new_settings.input.prevent_accidental_hard_drop = e.checked();
+                    set_game_settings(new_settings);
+                }
+            }
+            "Prevent Accidental Hard Drop"
+        }
+    }
+}
+
+/// IRS/IHS: rotate and hold keys held while a piece spawns apply to it.
+#[component]
+fn InitialInputCheckboxes() -> Element {
+    let old_settings = use_game_settings();
+    rsx! {
+        h5 {
+            "Initial Rotation / Hold"
+        }
+        label {
+            input {
+                type: "checkbox",
+                checked: old_settings.input.initial_rotation,
+                onchange: move |e| {
+                    let mut new_settings = old_settings;
+                    new_settings.input.initial_rotation = e.checked();
+                    set_game_settings(new_settings);
+                }
+            }
+            "Initial Rotation (IRS)"
+        }
+        label {
+            input {
+                type: "checkbox",
+                checked: old_settings.input.initial_hold,
+                onchange: move |e| {
+                    let mut new_settings = old_settings;
+                    new_settings.input.initial_hold = e.checked();
+                    set_game_settings(new_settings);
+                }
+            }
+            "Initial Hold (IHS)"
+        }
     }
 }
 
@@ -99,11 +233,12 @@ fn AfterRepeatDelaySlider() -> Element {
     let old_settings = use_game_settings();
     let old_init = old_settings.input.autorepeat_delay_after.as_millis() as u16;
 
-    let label_initial = use_signal(|| "Repeat Delay (ms)".to_string());
+    let label_initial =
+        use_signal(|| "Repeat Delay (ms, 0 = instant)".to_string());
     let slidr_delay_after = use_signal(|| old_init);
     use_effect(move || {
         let init = *slidr_delay_after.read();
-        let init = init.clamp(4, 666);
+        let init = init.clamp(0, 666);
         if init != old_init {
             let mut new_settings = old_settings;
             new_settings.input.autorepeat_delay_after =
@@ -119,7 +254,7 @@ fn AfterRepeatDelaySlider() -> Element {
             label: label_initial,
             m: slidr_delay_after,
             default_value: 33,
-            min: 4,
+            min: 0,
             max: 666
         }
     }
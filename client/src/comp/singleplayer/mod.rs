@@ -1,3 +1,6 @@
+mod practice;
+pub use practice::*;
+
 use dioxus::prelude::*;
 use std::sync::Arc;
 
@@ -45,6 +48,7 @@ pub fn GameBoardInputAndDisplay(
                 get_timestamp_now_ms(),
             );
             let mut s = use_game_settings();
+            game_state_manager.set_input_settings(&s.input);
             let arc_s = Arc::new(RwLock::new(s));
             let callback_manager = InputCallbackManagerRule::new(
                 _r,
@@ -0,0 +1,149 @@
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+use futures_util::pin_mut;
+use game::{
+    futures_channel,
+    input::{
+        callback_manager::InputCallbackManagerRule,
+        events::{GameInputEvent, GameInputEventKey, GameInputEventType},
+    },
+    state_manager::GameStateManager,
+    tet::{get_random_seed, GameRules, GameState},
+    timestamp::get_timestamp_now_ms,
+};
+use n0_future::{task::AbortOnDropHandle, StreamExt};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::{
+    comp::{game_display::GameDisplay, input::GameInputCaptureParent},
+    localstorage::use_game_settings,
+};
+
+/// How many pieces back undo can go.
+const PRACTICE_MAX_HISTORY: usize = 100;
+
+#[derive(Clone, Debug)]
+enum PracticeCommand {
+    Input(GameInputEvent),
+    JumpToPiece(u16),
+}
+
+/// Unranked single player game with undo, redo and jump to a piece.
+#[component]
+pub fn PracticeGameBoard() -> Element {
+    let mut game_state = use_signal(GameState::new_random);
+    let mut piece_ids = use_signal(Vec::<u16>::new);
+
+    let practice = use_coroutine(
+        move |mut _r: UnboundedReceiver<PracticeCommand>| async move {
+            let (input_tx, input_rx) = futures_channel::mpsc::unbounded();
+            let mut game_state_manager = GameStateManager::new_practice(
+                &get_random_seed(),
+                get_timestamp_now_ms(),
+                GameRules::default(),
+                PRACTICE_MAX_HISTORY,
+            );
+            let mut s = use_game_settings();
+            game_state_manager.set_input_settings(&s.input);
+            let arc_s = Arc::new(RwLock::new(s));
+            let callback_manager = InputCallbackManagerRule::new(
+                input_rx,
+                game_state_manager.read_state_stream(),
+                arc_s.clone(),
+            );
+
+            game_state_manager
+                .add_rule("callback_manager", Arc::new(callback_manager));
+
+            let g2 = game_state_manager.clone();
+            let main_loop =
+                AbortOnDropHandle::new(n0_future::task::spawn(async move {
+                    g2.main_loop().await
+                }));
+
+            // practice keys go to the manager, the rest to the game
+            let g3 = game_state_manager.clone();
+            let commands =
+                AbortOnDropHandle::new(n0_future::task::spawn(async move {
+                    while let Some(command) = _r.next().await {
+                        match command {
+                            PracticeCommand::Input(event) => {
+                                if event.event != GameInputEventType::KeyDown {
+                                    let _ = input_tx.unbounded_send(event);
+                                    continue;
+                                }
+                                match event.key {
+                                    GameInputEventKey::PracticeUndo => {
+                                        g3.undo().await;
+                                    }
+                                    GameInputEventKey::PracticeRedo => {
+                                        g3.redo().await;
+                                    }
+                                    GameInputEventKey::PracticeRestart => {
+                                        let ids = g3.history_piece_ids().await;
+                                        if let Some(first) = ids.first() {
+                                            g3.jump_to_piece(*first).await;
+                                        }
+                                    }
+                                    _ => {
+                                        let _ = input_tx.unbounded_send(event);
+                                    }
+                                }
+                            }
+                            PracticeCommand::JumpToPiece(piece_id) => {
+                                g3.jump_to_piece(piece_id).await;
+                            }
+                        }
+                    }
+                }));
+
+            let stream = game_state_manager.read_state_stream();
+            pin_mut!(stream);
+            while let Some(next_state) = stream.next().await {
+                game_state.set(next_state);
+                piece_ids.set(game_state_manager.history_piece_ids().await);
+                let s2 = use_game_settings();
+                if s2 != s {
+                    s = s2;
+                    *arc_s.write().await = s;
+                }
+            }
+            warn!("practice game state stream finished");
+            drop(commands);
+            drop(main_loop);
+        },
+    );
+
+    let on_user_event = Callback::new(move |event: GameInputEvent| {
+        practice.send(PracticeCommand::Input(event));
+    });
+    rsx! {
+        div {
+            style: "display: flex; flex-direction: column; width: 100%; height: 100%;",
+            div {
+                style: "flex-grow: 1;",
+                GameInputCaptureParent {
+                    on_user_event,
+
+                    GameDisplay { game_state }
+                }
+            }
+            div {
+                style: "display: flex; flex-wrap: wrap; gap: 2px;",
+                for piece_id in piece_ids.read().clone() {
+                    button {
+                        key: "{piece_id}",
+                        class: "outline secondary",
+                        style: "padding: 2px 6px;",
+                        onclick: move |_| {
+                            practice.send(PracticeCommand::JumpToPiece(piece_id));
+                        },
+                        small { "{piece_id}" }
+                    }
+                }
+            }
+        }
+    }
+}
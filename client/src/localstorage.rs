@@ -58,7 +58,7 @@ pub fn LocalStorageParent(children: Element) -> Element {
     let game_settings = use_memo(move || *game_settings_w.read());
 
     let button_settings_w = use_synced_storage::<LocalStorage, ButtonSettings>(
-        "button_settings_1".to_string(),
+        "button_settings_2".to_string(),
         ButtonSettings::default,
     );
     let button_settings = use_memo(move || button_settings_w.read().clone());
@@ -16,6 +16,9 @@ pub use i_am_a_robot_singleplayer::*;
 mod singleplayer;
 pub use singleplayer::*;
 
+mod practice;
+pub use practice::*;
+
 mod main_settings;
 pub use main_settings::*;
 
@@ -228,6 +228,7 @@ fn RoomControlAdmin(
                           users,
                            title: "Private 1v1 {room_id}".to_string(),
                            type_: game::api::game_match::GameMatchType::_1v1,
+                           rules: game::tet::GameRules::default(),
                     };
                     if let Err(e) = chat3_send.broadcast_message(PrivateLobbyMessage::StartPlaying(_match.clone())).await {
                         tracing::error!("failed to send start playing message: {e:?}");
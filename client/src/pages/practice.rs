@@ -0,0 +1,14 @@
+use dioxus::prelude::*;
+
+use crate::comp::singleplayer::PracticeGameBoard;
+
+/// Unranked practice game, with undo and redo
+#[component]
+pub fn PlayPracticePage() -> Element {
+    rsx! {
+        article {
+            style: "height: 80dvh; display: flex;",
+            PracticeGameBoard {}
+        }
+    }
+}
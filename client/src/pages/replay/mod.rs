@@ -1,9 +1,8 @@
-use std::collections::HashMap;
-
+use base64::{prelude::BASE64_STANDARD, Engine};
 use dioxus::prelude::*;
-use game::tet::GameState;
+use game::tet::{GameReplayFile, KeyframedReplay, REPLAY_KEYFRAME_INTERVAL};
 use protocol::api::api_declarations::{
-    GetGameStateRowsForMatch, GetReplayMatchDetail, GetReplayMatchList,
+    GetKeyframedReplaysForMatch, GetReplayMatchDetail, GetReplayMatchList,
     MatchRow2,
 };
 
@@ -45,6 +44,10 @@ pub fn ReplayHomePage() -> Element {
             h1 {
                 "Replay Match List"
             }
+            Link {
+                to: Route::ReplayFilePage {},
+                "Open a .replay file"
+            }
             ul {
                 for d in data.read().clone().iter() {
                     li {
@@ -109,7 +112,7 @@ fn DisplayReplayDetails(match_info: ReadSignal<MatchRow2>) -> Element {
     let match_info = use_memo(move || match_info.read().clone());
     let match_info = match_info.read().clone();
     let err = use_signal(String::new);
-    let mut match_row = use_signal(std::vec::Vec::new);
+    let mut replays = use_signal(std::vec::Vec::new);
 
     let api = use_context::<NetworkState>().client_api_manager;
     let api = api.read().clone();
@@ -123,28 +126,15 @@ fn DisplayReplayDetails(match_info: ReadSignal<MatchRow2>) -> Element {
         let api2 = api2.clone();
         let match_id = m2.clone();
         async move {
-            let Ok(r) =
-                api2.call_method::<GetGameStateRowsForMatch>(match_id).await
+            let Ok(r) = api2
+                .call_method::<GetKeyframedReplaysForMatch>(match_id)
+                .await
             else {
                 tracing::warn!("call error!");
                 return;
             };
-            match_row.set(r);
-        }
-    });
-
-    let rows2 = use_memo(move || {
-        let rows1 = match_row.read().clone();
-        let mut rows2 = HashMap::<String, Vec<GameState>>::new();
-        for row in rows1 {
-            let ins = rows2.entry(row.user_id).or_default();
-            let Some(state) = &row.state_data else {
-                continue;
-            };
-            ins.push(*state);
+            replays.set(r);
         }
-
-        rows2
     });
 
     rsx! {
@@ -156,7 +146,7 @@ fn DisplayReplayDetails(match_info: ReadSignal<MatchRow2>) -> Element {
             div {
                 style: "width: 100%;
                  height: 100%; flex-direction:row; display:flex;",
-                for x in rows2.read().iter() {
+                for x in replays.read().iter() {
                     GameStateBrowser {data: (x.1).clone()}
                 }
             }
@@ -164,27 +154,37 @@ fn DisplayReplayDetails(match_info: ReadSignal<MatchRow2>) -> Element {
     }
 }
 
+/// Slider over a replay; states are rebuilt from the nearest keyframe.
 #[component]
-pub fn GameStateBrowser(data: ReadSignal<Vec<GameState>>) -> Element {
+pub fn GameStateBrowser(data: ReadSignal<KeyframedReplay>) -> Element {
     let idx = use_signal(move || 0);
-    let max = use_signal(move || {
-        let i = data.read().len() as i32;
-        tracing::warn!("\n \n\n MAXMAXMXAMX : {i} \n\n");
-        i
-    });
+    let max = use_memo(move || data.read().len() as i32);
 
     let state = use_memo(move || {
         let idx = *idx.read();
-        let vec = data.read();
-        let max_idx = vec.len();
-        if max_idx == 0 {
+        let replay = data.read();
+        if replay.is_empty() {
             return None;
         }
-        let mut idx = idx as usize;
-        if idx >= max_idx {
-            idx = max_idx - 1;
+        match replay.seek(idx.max(0) as usize) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                tracing::warn!("cannot seek replay: {e:#}");
+                None
+            }
         }
-        vec.get(idx).cloned()
+    });
+
+    let download_href = use_memo(move || {
+        let file = match GameReplayFile::from_segments(data.read().segments()) {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::warn!("cannot make replay file: {e:#}");
+                return None;
+            }
+        };
+        let bytes = BASE64_STANDARD.encode(file.to_bytes());
+        Some(format!("data:application/octet-stream;base64,{bytes}"))
     });
 
     rsx! {
@@ -198,6 +198,13 @@ pub fn GameStateBrowser(data: ReadSignal<Vec<GameState>>) -> Element {
                 max: *max.read()
             }
             "max: {*max.read()}"
+            if let Some(href) = download_href.read().as_ref() {
+                a {
+                    href: "{href}",
+                    download: "game.replay",
+                    "Download .replay"
+                }
+            }
 
             div {
                 style: "width: 100%; height: 80%;border:1px solid black;",
@@ -208,3 +215,55 @@ pub fn GameStateBrowser(data: ReadSignal<Vec<GameState>>) -> Element {
         }
     }
 }
+
+#[component]
+pub fn ReplayFilePage() -> Element {
+    let mut err = use_signal(String::new);
+    let mut replay = use_signal(move || None);
+
+    rsx! {
+        h1 {
+            "Open a .replay file"
+        }
+        input {
+            r#type: "file",
+            accept: ".replay",
+            onchange: move |evt: FormEvent| async move {
+                let Some(file) = evt.files().into_iter().next() else {
+                    return;
+                };
+                let bytes = match file.read_bytes().await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        err.set(format!("cannot read file: {e}"));
+                        return;
+                    }
+                };
+                let loaded = GameReplayFile::from_bytes(&bytes).and_then(|file| {
+                    KeyframedReplay::from_segments(
+                        file.to_segments()?,
+                        REPLAY_KEYFRAME_INTERVAL,
+                    )
+                });
+                match loaded {
+                    Ok(r) => {
+                        err.set(String::new());
+                        replay.set(Some(r));
+                    }
+                    Err(e) => err.set(format!("bad replay file: {e:#}")),
+                }
+            }
+        }
+        if !err.read().is_empty() {
+            p {
+                {err}
+            }
+        }
+        if let Some(r) = replay.read().as_ref() {
+            div {
+                style: "width: 100%; height: 100%; display:flex;",
+                GameStateBrowser {data: r.clone()}
+            }
+        }
+    }
+}
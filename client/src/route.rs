@@ -59,6 +59,9 @@ pub enum Route {
         #[route("/singleplayer")]
         PlaySingleplayerPage {},
 
+        #[route("/practice")]
+        PlayPracticePage {},
+
         #[route("/1v1/:game_match")]
         Play1v1Page {game_match: UrlParam<GameMatch<NodeIdentity>>},
 
@@ -74,6 +77,9 @@ pub enum Route {
         #[route("/replays/1v1_match/:match_id")]
         Replay1v1Match {match_id: String},
 
+        #[route("/replays/file")]
+        ReplayFilePage {},
+
 
         #[route("/private_lobby/:owner_id/:room_uuid")]
         PrivateLobbyPage{owner_id: UrlParam<NodeIdentity>, room_uuid: uuid::Uuid},
@@ -104,6 +104,7 @@ impl Page for SingleplayerPage {
             KeyCode::Modifier(ModifierKeyCode::RightControl) => {
                 Some(GameInputEventKey::RotateRight)
             }
+            KeyCode::Char('a') => Some(GameInputEventKey::Rotate180),
 
             KeyCode::Left => Some(GameInputEventKey::MoveLeft),
             KeyCode::Right => Some(GameInputEventKey::MoveRight),
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-use crate::tet::GameSeed;
+use crate::tet::{
+    decode_fumen, GameReplayInit, GameRules, GameSeed, GameSetup, GarbageRules,
+};
 
 #[derive(
     Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
@@ -12,6 +14,48 @@ pub struct GameMatch<User> {
     pub users: Vec<User>,
     pub title: String,
     pub type_: GameMatchType,
+    pub rules: GameRules,
+}
+
+impl<User> GameMatch<User> {
+    /// Start of every player's game in this match.
+    pub fn replay_init(&self) -> GameReplayInit {
+        GameReplayInit {
+            setup: self.setup(),
+            ..GameReplayInit::empty(&self.seed, self.time, self.rules)
+        }
+    }
+
+    /// Starting position of the games of a setup match.
+    pub fn setup(&self) -> Option<GameSetup> {
+        GameMatchType::get_match_setup(&Some(self.type_.clone()))
+    }
+}
+
+/// `GameMatch` as it was saved with `data_version` 0, before matches had
+/// rules.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LegacyGameMatch<User> {
+    pub match_id: uuid::Uuid,
+    pub seed: GameSeed,
+    pub time: i64,
+    pub users: Vec<User>,
+    pub title: String,
+    pub type_: GameMatchType,
+}
+
+impl<User> From<LegacyGameMatch<User>> for GameMatch<User> {
+    fn from(old: LegacyGameMatch<User>) -> Self {
+        Self {
+            match_id: old.match_id,
+            seed: old.seed,
+            time: old.time,
+            users: old.users,
+            title: old.title,
+            type_: old.type_,
+            rules: GameRules::legacy(),
+        }
+    }
 }
 
 #[derive(
@@ -58,6 +102,10 @@ pub enum GameMatchType {
     _10v10,
     _4v4,
     Blitz,
+    CheeseRace,
+    Survival,
+    /// starts from a preset board and queue, given as fumen data
+    Setup(String),
 }
 
 impl GameMatchType {
@@ -69,6 +117,9 @@ impl GameMatchType {
             Some(Self::Blitz) => "blitz".to_string(),
             Some(Self::_4v4) => "4v4".to_string(),
             Some(Self::_10v10) => "10v10".to_string(),
+            Some(Self::CheeseRace) => "cheese_race".to_string(),
+            Some(Self::Survival) => "survival".to_string(),
+            Some(Self::Setup(fumen)) => format!("setup_{}", fumen_to_url(fumen)),
             Some(Self::ManVsCar(_bot)) => format!(
                 "bot_{}
             ",
@@ -85,9 +136,13 @@ impl GameMatchType {
             "blitz" => Some(Self::Blitz),
             "4v4" => Some(Self::_4v4),
             "10v10" => Some(Self::_10v10),
+            "cheese_race" => Some(Self::CheeseRace),
+            "survival" => Some(Self::Survival),
             _ => {
                 if let Some(bot_name) = s.strip_prefix("bot_") {
                     Some(Self::ManVsCar(bot_name.to_string()))
+                } else if let Some(fumen) = s.strip_prefix("setup_") {
+                    Some(Self::Setup(fumen_from_url(fumen)))
                 } else {
                     anyhow::bail!("bad url!");
                 }
@@ -103,7 +158,71 @@ impl GameMatchType {
             Some(Self::Blitz) => 1,
             Some(Self::_4v4) => 8,
             Some(Self::_10v10) => 20,
+            Some(Self::CheeseRace) => 1,
+            Some(Self::Survival) => 1,
+            Some(Self::Setup(_)) => 1,
             Some(Self::ManVsCar(_bot)) => 1,
         }
     }
+
+    pub fn get_match_rules(item: &Option<Self>) -> GameRules {
+        match item {
+            Some(Self::CheeseRace) => GameRules {
+                garbage: GarbageRules {
+                    initial_rows: 10,
+                    ..GarbageRules::cheese()
+                },
+                ..Default::default()
+            },
+            Some(Self::Survival) => GameRules {
+                garbage: GarbageRules {
+                    line_messiness: 30,
+                    ..GarbageRules::clean()
+                },
+                ..Default::default()
+            },
+            _ => GameRules::default(),
+        }
+    }
+
+    /// Starting position of setup games, `None` for the other match types
+    /// or when the fumen data can not be read.
+    pub fn get_match_setup(item: &Option<Self>) -> Option<GameSetup> {
+        match item {
+            Some(Self::Setup(fumen)) => {
+                let page = decode_fumen(fumen).ok()?.into_iter().next()?;
+                Some(GameSetup::from_fumen_page(&page))
+            }
+            _ => None,
+        }
+    }
+
+    /// Single player modes ranked by their result instead of elo.
+    pub fn is_solo_mode(&self) -> bool {
+        matches!(
+            self,
+            Self::_40lines
+                | Self::Blitz
+                | Self::CheeseRace
+                | Self::Survival
+                | Self::Setup(_)
+        )
+    }
+}
+
+/// Fumen data uses `+` and `/`, which do not belong in a path segment.
+fn fumen_to_url(fumen: &str) -> String {
+    fumen
+        .chars()
+        .filter(|c| *c != '?')
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn fumen_from_url(s: &str) -> String {
+    s.replace('-', "+").replace('_', "/")
 }
@@ -5,7 +5,8 @@ use super::input_manager::{CallbackMoveType, CallbackTicket, UserEvent};
 use crate::input::input_manager::GameInputManager;
 use crate::settings::GameSettings;
 use crate::tet::GameState;
-use crate::{tet::TetAction, timestamp::get_timestamp_now_ms};
+use crate::tet::TetAction;
+use crate::timestamp::{real_clock, SharedClock};
 use async_stream::stream;
 use futures_channel::mpsc::{unbounded, UnboundedReceiver};
 use futures_core::stream::Stream;
@@ -23,12 +24,13 @@ struct CallbackManager {
 
 impl CallbackManager {
     // pub fn
-    pub fn new2() -> Self {
+    pub fn new2(clock: SharedClock) -> Self {
         let notify = Arc::new(Notify::new());
         let _notify = notify.clone();
         let mut inner = CallbackManagerInner {
             events: BTreeMap::new(),
             notify,
+            clock,
         };
         inner.set_cb(CallbackMoveType::AutoSoftDrop, Duration::from_millis(100));
         Self {
@@ -44,20 +46,13 @@ impl CallbackManager {
         g.accept_user_event(user_event)
     }
 
-    // private fn
-
-    async fn get_events(&self) -> BTreeMap<CallbackMoveType, i64> {
-        let e = {
+    pub async fn get_sleep_duration_ms(&self) -> (i64, Vec<CallbackMoveType>) {
+        let (events, clock) = {
             let g = self.inner.read().await;
-            g.events.clone()
+            (g.events.clone(), g.clock.clone())
         };
-        e
-    }
 
-    pub async fn get_sleep_duration_ms(&self) -> (i64, Vec<CallbackMoveType>) {
-        let events = self.get_events().await;
-
-        let now = get_timestamp_now_ms();
+        let now = clock.now_ms();
         let mut min_delay = 10000;
         let mut v = vec![];
         for (event, timestamp_expired) in events {
@@ -76,11 +71,13 @@ impl CallbackManager {
     pub fn main_loop(
         &self,
         mut _r: UnboundedReceiver<(GameState, GameInputEvent)>,
+        mut state_r: UnboundedReceiver<GameState>,
         settings: Arc<RwLock<GameSettings>>,
     ) -> impl Stream<Item = TetAction> {
         let mut input_manager = GameInputManager::new();
         let callback_manager = self.clone();
         stream! {
+            let clock = callback_manager.inner.read().await.clock.clone();
             pin_mut!(_r);
             loop {
                 let game_settings = {*settings.read().await};
@@ -117,7 +114,21 @@ impl CallbackManager {
 
                         let settings =  {*settings.read().await};
                         let event = input_manager
-                            .on_user_keyboard_event(kbd_event, settings, &state);
+                            .on_user_keyboard_event(kbd_event, settings, &state, clock.now_ms());
+                        let y = callback_manager.accept_user_event(event).await;
+                        if let Some(action) = y {
+                            yield action;
+                        }
+                        continue;
+                    }
+                    new_state = state_r.next().fuse() => {
+                        let Some(new_state) = new_state else {
+                            tracing::warn!("ticket manger loop end: no more states");
+                            break;
+                        };
+                        let settings = {*settings.read().await};
+                        let event = input_manager
+                            .on_new_state(&new_state, settings, clock.now_ms());
                         let y = callback_manager.accept_user_event(event).await;
                         if let Some(action) = y {
                             yield action;
@@ -128,7 +139,7 @@ impl CallbackManager {
                         continue;
                     }
 
-                    _sl = n0_future::time::sleep(duration).fuse() => {
+                    _sl = clock.sleep(duration).fuse() => {
                         // let t1 = get_timestamp_now_ms();
                         // let dt = t1 - t0;
                         // let diff = dt as i32 - duration_ms as i32;
@@ -146,6 +157,7 @@ impl CallbackManager {
 struct CallbackManagerInner {
     events: BTreeMap<CallbackMoveType, i64>,
     notify: Arc<Notify>,
+    clock: SharedClock,
 }
 
 impl CallbackManagerInner {
@@ -177,7 +189,7 @@ impl CallbackManagerInner {
         self.notify.notify_waiters();
     }
     fn set_cb(&mut self, move_type: CallbackMoveType, duration: Duration) {
-        let now = get_timestamp_now_ms();
+        let now = self.clock.now_ms();
         self.events
             .insert(move_type, now + duration.as_millis() as i64);
         self.notify.notify_waiters();
@@ -192,17 +204,28 @@ pub struct InputCallbackManagerRule {
     _main_loop: AbortOnDropHandle<anyhow::Result<()>>,
     _stream_loop: AbortOnDropHandle<anyhow::Result<()>>,
     action_receiver: Mutex<UnboundedReceiver<TetAction>>,
+    clock: SharedClock,
 }
 
 impl InputCallbackManagerRule {
     pub fn new(
+        input_stream: UnboundedReceiver<GameInputEvent>,
+        state_stream: impl Stream<Item = GameState> + Send + 'static,
+        settings: Arc<RwLock<GameSettings>>,
+    ) -> Self {
+        Self::new_with_clock(input_stream, state_stream, settings, real_clock())
+    }
+    /// Timers and action timestamps follow `clock` instead of the wall clock.
+    pub fn new_with_clock(
         mut input_stream: UnboundedReceiver<GameInputEvent>,
         state_stream: impl Stream<Item = GameState> + Send + 'static,
         settings: Arc<RwLock<GameSettings>>,
+        clock: SharedClock,
     ) -> Self {
-        let cb_manager = CallbackManager::new2();
+        let cb_manager = CallbackManager::new2(clock.clone());
 
         let (pair_tx, pair_rx) = unbounded();
+        let (state_tx, state_rx) = unbounded();
         let (action_tx, action_rx) = unbounded();
 
         let stream_loop = AbortOnDropHandle::new(n0_future::task::spawn(async move {
@@ -210,6 +233,7 @@ impl InputCallbackManagerRule {
             let Some(mut state) = state_stream.next().await else {
                 anyhow::bail!("no initial state.");
             };
+            state_tx.unbounded_send(state)?;
 
             loop {
                 tokio::select! {
@@ -224,6 +248,7 @@ impl InputCallbackManagerRule {
                             anyhow::bail!("no more states.");
                         };
                         state = new_state ;
+                        state_tx.unbounded_send(state)?;
                     }
                 }
             }
@@ -232,7 +257,7 @@ impl InputCallbackManagerRule {
         Self {
             _cb_manager: cb_manager.clone(),
             _main_loop: AbortOnDropHandle::new(n0_future::task::spawn(async move {
-                let mut s = cb_manager.main_loop(pair_rx, settings);
+                let mut s = cb_manager.main_loop(pair_rx, state_rx, settings);
                 while let Some(x) = s.next().await {
                     action_tx.unbounded_send(x)?;
                 }
@@ -241,6 +266,7 @@ impl InputCallbackManagerRule {
             })),
             _stream_loop: stream_loop,
             action_receiver: Mutex::new(action_rx),
+            clock,
         }
     }
 }
@@ -262,7 +288,7 @@ impl RuleManager for InputCallbackManagerRule {
                 );
                 anyhow::bail!("no next action");
             };
-            let Ok(next_state) = _state.try_action(next_action, get_timestamp_now_ms())
+            let Ok(next_state) = _state.try_action(next_action, self.clock.now_ms())
             else {
                 // tracing::info!("InputCallbackManagerRule NO ACTION (error).");
                 continue;
@@ -271,3 +297,43 @@ impl RuleManager for InputCallbackManagerRule {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::events::{GameInputEventKey, GameInputEventType};
+    use crate::timestamp::ManualClock;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn timers_follow_the_clock() {
+        let clock = Arc::new(ManualClock::new(0));
+        let cb_manager = CallbackManager::new2(clock.clone());
+        let settings = GameSettings::default();
+        let das = settings.input.autorepeat_delay_initial.as_millis() as i64;
+        let state = GameState::new(&[0; 32], 0);
+
+        let mut input_manager = GameInputManager::new();
+        let event = input_manager.on_user_keyboard_event(
+            GameInputEvent {
+                key: GameInputEventKey::MoveLeft,
+                event: GameInputEventType::KeyDown,
+                ts: chrono::Utc::now(),
+            },
+            settings,
+            &state,
+            0,
+        );
+        let action = cb_manager.accept_user_event(event).now_or_never().unwrap();
+        assert_eq!(action, Some(TetAction::MoveLeft));
+
+        clock.advance(Duration::from_millis(das as u64 - 1));
+        let (_, due) = cb_manager.get_sleep_duration_ms().now_or_never().unwrap();
+        assert!(!due.contains(&CallbackMoveType::RepeatMoveLeft));
+
+        clock.advance(Duration::from_millis(1));
+        let (_, due) = cb_manager.get_sleep_duration_ms().now_or_never().unwrap();
+        assert!(due.contains(&CallbackMoveType::RepeatMoveLeft));
+    }
+}
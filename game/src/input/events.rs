@@ -6,6 +6,7 @@
 // - Hold,
 // - RotateLeft,
 // - RotateRight,
+// - Rotate180,
 //
 // -- ESCAPE
 // -- PAUSE
@@ -61,12 +62,17 @@ pub enum GameInputEventKey {
     Hold,
     RotateLeft,
     RotateRight,
+    Rotate180,
     // menu
     MenuEscape,
     MenuPause,
     MenuMuteSound,
     MenuZoomIn,
     MenuZoomOut,
+    // practice games only
+    PracticeUndo,
+    PracticeRedo,
+    PracticeRestart,
     // ???
     NoOp,
 }
@@ -81,6 +87,7 @@ impl GameInputEventKey {
             GameInputEventKey::Hold => Some(TetAction::Hold),
             GameInputEventKey::RotateLeft => Some(TetAction::RotateLeft),
             GameInputEventKey::RotateRight => Some(TetAction::RotateRight),
+            GameInputEventKey::Rotate180 => Some(TetAction::Rotate180),
             _ => None,
         }
     }
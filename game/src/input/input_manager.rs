@@ -5,10 +5,23 @@ use crate::input::events::GameInputEvent;
 use crate::settings::GameSettings;
 use crate::tet::{GameState, TetAction};
 
+/// Repeat interval of the wall shift and instant soft drop callbacks, so a
+/// held key also moves the next piece.
+const INSTANT_REPEAT_INTERVAL: Duration = Duration::from_millis(16);
+/// Hard drops this soon after a piece spawned on its own are ignored when
+/// `prevent_accidental_hard_drop` is set.
+pub const ACCIDENTAL_HARD_DROP_WINDOW_MS: i64 = 150;
+
 #[derive(Clone, Debug)]
 pub struct GameInputManager {
     new_held: BTreeSet<TetAction>,
     old_held: BTreeSet<TetAction>,
+    gravity_interval: Option<Duration>,
+    /// last piece seen by `on_new_state`, to spot spawns
+    last_pcs_id: Option<u16>,
+    /// move keys held past the initial repeat delay, for the DAS cut
+    charged: BTreeSet<TetAction>,
+    hard_drop_blocked_until: i64,
 }
 
 impl Default for GameInputManager {
@@ -22,6 +35,10 @@ impl GameInputManager {
         Self {
             new_held: BTreeSet::new(),
             old_held: BTreeSet::new(),
+            gravity_interval: None,
+            last_pcs_id: None,
+            charged: BTreeSet::new(),
+            hard_drop_blocked_until: i64::MIN,
         }
     }
 
@@ -30,6 +47,7 @@ impl GameInputManager {
         user_keyboard_event: GameInputEvent,
         game_settings: GameSettings,
         game_state: &GameState,
+        now: i64,
     ) -> UserEvent {
         let GameInputEvent {
             key,
@@ -61,6 +79,9 @@ impl GameInputManager {
         self.old_held = self.new_held.clone();
 
         let mut cb = vec![];
+        for key in _new_up.iter().chain(new_down.iter()) {
+            self.charged.remove(key);
+        }
         for key_up in _new_up {
             let move_type = match key_up {
                 TetAction::MoveLeft => CallbackMoveType::RepeatMoveLeft,
@@ -81,10 +102,14 @@ impl GameInputManager {
                 TetAction::UserSoftDrop => CallbackMoveType::RepeatMoveDown,
                 _ => continue,
             };
+            let delay = match move_type {
+                CallbackMoveType::RepeatMoveDown => {
+                    self.soft_drop_repeat_delay(game_settings, true)
+                }
+                _ => game_settings.input.autorepeat_delay_initial,
+            };
             cb.push(CallbackTicket {
-                request_type: CallbackRequestType::SetCallback(
-                    game_settings.input.autorepeat_delay_initial,
-                ),
+                request_type: CallbackRequestType::SetCallback(delay),
                 move_type,
             })
         }
@@ -96,18 +121,144 @@ impl GameInputManager {
         {
             cb.push(CallbackTicket {
                 request_type: CallbackRequestType::SetCallback(
-                    game_settings.game.auto_softdrop_interval,
+                    game_state
+                        .gravity_interval()
+                        .unwrap_or(game_settings.game.auto_softdrop_interval),
                 ),
                 move_type: CallbackMoveType::AutoSoftDrop,
             });
         }
 
-        let event = UserEvent {
+        let action = match new_down.first().cloned() {
+            Some(TetAction::UserSoftDrop) if game_settings.input.instant_soft_drop => {
+                Some(TetAction::SoftDropToFloor)
+            }
+            Some(TetAction::HardDrop) if now < self.hard_drop_blocked_until => {
+                tracing::info!("hard drop ignored, the piece just spawned");
+                None
+            }
+            action => action,
+        };
+
+        UserEvent {
             callback_tickets: cb,
-            action: new_down.first().cloned(),
+            action,
+        }
+    }
+
+    /// Keep the lock delay callback in sync with the game: armed for the
+    /// moment the current piece locks, dropped while it is in the air.
+    /// Also picks up the gravity of the current level, and plays IRS/IHS on
+    /// a new piece. `now` is the time of the clock the callbacks run on.
+    pub fn on_new_state(
+        &mut self,
+        game_state: &GameState,
+        game_settings: GameSettings,
+        now: i64,
+    ) -> UserEvent {
+        self.gravity_interval = game_state.gravity_interval();
+        let deadline = game_state.lock_delay.deadline(&game_state.rules.lock_delay);
+        let request_type = match deadline {
+            Some(deadline) if !game_state.game_over() => {
+                let wait_ms = (deadline - now).max(0) + 1;
+                CallbackRequestType::SetCallback(Duration::from_millis(wait_ms as u64))
+            }
+            _ => CallbackRequestType::DropCallback,
         };
+        let mut callback_tickets = vec![CallbackTicket {
+            request_type,
+            move_type: CallbackMoveType::LockDelay,
+        }];
+        let mut action = None;
+        let spawned = game_state
+            .current_pcs
+            .filter(|pcs| self.last_pcs_id != Some(pcs.id));
+        if let Some(pcs) = spawned.filter(|_| !game_state.game_over()) {
+            self.last_pcs_id = Some(pcs.id);
+            callback_tickets.extend(self.das_cut(game_settings));
+            // the last piece locked by itself, a hard drop now was meant for it
+            if game_settings.input.prevent_accidental_hard_drop
+                && !matches!(
+                    game_state.last_action,
+                    TetAction::HardDrop | TetAction::Hold | TetAction::Nothing
+                )
+            {
+                self.hard_drop_blocked_until = now + ACCIDENTAL_HARD_DROP_WINDOW_MS;
+            }
+            action = self.initial_action(game_state, game_settings);
+        }
+        UserEvent {
+            action,
+            callback_tickets,
+        }
+    }
 
-        event
+    /// DAS cut: charged move keys wait `das_cut_delay` before shifting a new
+    /// piece.
+    fn das_cut(&self, game_settings: GameSettings) -> Vec<CallbackTicket> {
+        let delay = game_settings.input.das_cut_delay;
+        if delay.is_zero() {
+            return vec![];
+        }
+        self.charged
+            .iter()
+            .filter_map(|key| match key {
+                TetAction::MoveLeft => Some(CallbackMoveType::RepeatMoveLeft),
+                TetAction::MoveRight => Some(CallbackMoveType::RepeatMoveRight),
+                _ => None,
+            })
+            .map(|move_type| CallbackTicket {
+                request_type: CallbackRequestType::SetCallback(delay),
+                move_type,
+            })
+            .collect()
+    }
+
+    /// Delay of the soft drop repeat: instant, the gravity divided by the
+    /// soft drop factor, or the move key repeat delays without a factor.
+    fn soft_drop_repeat_delay(
+        &self,
+        game_settings: GameSettings,
+        first: bool,
+    ) -> Duration {
+        let input = game_settings.input;
+        if input.instant_soft_drop {
+            INSTANT_REPEAT_INTERVAL
+        } else if input.soft_drop_factor > 0 {
+            let gravity = self
+                .gravity_interval
+                .unwrap_or(game_settings.game.auto_softdrop_interval);
+            (gravity / input.soft_drop_factor as u32).max(Duration::from_millis(1))
+        } else if first {
+            input.autorepeat_delay_initial
+        } else {
+            input.autorepeat_delay_after
+        }
+    }
+
+    /// IRS/IHS: hold and rotate keys still held when a piece spawns act on
+    /// it at once instead of waiting for the next key-down. They are played
+    /// as normal actions, so replays record them. A hold spawns another
+    /// piece, which can then get the initial rotation.
+    fn initial_action(
+        &self,
+        game_state: &GameState,
+        game_settings: GameSettings,
+    ) -> Option<TetAction> {
+        let mut actions = vec![];
+        if game_settings.input.initial_hold {
+            actions.push(TetAction::Hold);
+        }
+        if game_settings.input.initial_rotation {
+            actions.extend([
+                TetAction::RotateLeft,
+                TetAction::RotateRight,
+                TetAction::Rotate180,
+            ]);
+        }
+        actions.into_iter().find(|action| {
+            self.new_held.contains(action) && game_state.try_action(*action, 0).is_ok()
+        })
     }
 
     pub fn callback_after_wait(
@@ -115,29 +266,48 @@ impl GameInputManager {
         callback_move_type: CallbackMoveType,
         game_settings: GameSettings,
     ) -> UserEvent {
+        let input = game_settings.input;
+        // an auto-repeat rate of zero shifts to the wall at once
+        let arr_zero = input.autorepeat_delay_after.is_zero();
         let action = match callback_move_type {
+            CallbackMoveType::RepeatMoveDown if input.instant_soft_drop => {
+                TetAction::SoftDropToFloor
+            }
             CallbackMoveType::RepeatMoveDown => TetAction::UserSoftDrop,
+            CallbackMoveType::RepeatMoveLeft if arr_zero => TetAction::MoveLeftToWall,
             CallbackMoveType::RepeatMoveLeft => TetAction::MoveLeft,
+            CallbackMoveType::RepeatMoveRight if arr_zero => TetAction::MoveRightToWall,
             CallbackMoveType::RepeatMoveRight => TetAction::MoveRight,
             CallbackMoveType::AutoSoftDrop => TetAction::AutoSoftDrop,
+            CallbackMoveType::LockDelay => TetAction::AutoSoftDrop,
         };
         let mut cb = vec![];
 
-        let cb_duration = match callback_move_type {
+        let request_type = match callback_move_type {
             // TODO: if game's next soft drop will lock, put a longer timeout here
-            CallbackMoveType::AutoSoftDrop => game_settings.game.auto_softdrop_interval,
-            CallbackMoveType::RepeatMoveDown => {
-                game_settings.input.autorepeat_delay_after
-            }
-            CallbackMoveType::RepeatMoveLeft => {
-                game_settings.input.autorepeat_delay_after
-            }
-            CallbackMoveType::RepeatMoveRight => {
-                game_settings.input.autorepeat_delay_after
+            CallbackMoveType::AutoSoftDrop => CallbackRequestType::SetCallback(
+                self.gravity_interval
+                    .unwrap_or(game_settings.game.auto_softdrop_interval),
+            ),
+            CallbackMoveType::RepeatMoveDown => CallbackRequestType::SetCallback(
+                self.soft_drop_repeat_delay(game_settings, false),
+            ),
+            CallbackMoveType::RepeatMoveLeft | CallbackMoveType::RepeatMoveRight => {
+                self.charged.insert(match callback_move_type {
+                    CallbackMoveType::RepeatMoveLeft => TetAction::MoveLeft,
+                    _ => TetAction::MoveRight,
+                });
+                CallbackRequestType::SetCallback(if arr_zero {
+                    INSTANT_REPEAT_INTERVAL
+                } else {
+                    input.autorepeat_delay_after
+                })
             }
+            // one shot, re-armed by `on_new_state`
+            CallbackMoveType::LockDelay => CallbackRequestType::DropCallback,
         };
         cb.push(CallbackTicket {
-            request_type: CallbackRequestType::SetCallback(cb_duration),
+            request_type,
             move_type: callback_move_type,
         });
 
@@ -177,6 +347,7 @@ pub enum CallbackMoveType {
     RepeatMoveLeft,
     RepeatMoveRight,
     RepeatMoveDown,
+    LockDelay,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -184,3 +355,129 @@ pub enum CallbackRequestType {
     SetCallback(Duration),
     DropCallback,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::events::{GameInputEventKey, GameInputEventType};
+    use wasm_bindgen_test::*;
+
+    fn key_down(key: GameInputEventKey) -> GameInputEvent {
+        GameInputEvent {
+            key,
+            event: GameInputEventType::KeyDown,
+            ts: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn held_keys_act_on_spawn() {
+        let mut settings = GameSettings::default();
+        let mut input = GameInputManager::new();
+        let mut state = GameState::new(&[1; 32], 0);
+        assert_eq!(input.on_new_state(&state, settings, 0).action, None);
+
+        let event = input.on_user_keyboard_event(
+            key_down(GameInputEventKey::RotateRight),
+            settings,
+            &state,
+            0,
+        );
+        assert_eq!(event.action, Some(TetAction::RotateRight));
+        state = state.try_action(TetAction::RotateRight, 10).unwrap();
+        assert_eq!(input.on_new_state(&state, settings, 10).action, None);
+
+        // turned off: the held key waits for the next key-down
+        state = state.try_action(TetAction::HardDrop, 20).unwrap();
+        assert_eq!(input.on_new_state(&state, settings, 20).action, None);
+
+        settings.input.initial_rotation = true;
+        settings.input.initial_hold = true;
+        state = state.try_action(TetAction::HardDrop, 30).unwrap();
+        let action = input.on_new_state(&state, settings, 30).action;
+        assert_eq!(action, Some(TetAction::RotateRight));
+        state = state.try_action(action.unwrap(), 30).unwrap();
+        assert_eq!(input.on_new_state(&state, settings, 30).action, None);
+
+        let event = input.on_user_keyboard_event(
+            key_down(GameInputEventKey::Hold),
+            settings,
+            &state,
+            30,
+        );
+        assert_eq!(event.action, Some(TetAction::Hold));
+        state = state.try_action(TetAction::Hold, 40).unwrap();
+        // the held piece can not be held again, the rotation still applies
+        let action = input.on_new_state(&state, settings, 40).action;
+        assert_eq!(action, Some(TetAction::RotateRight));
+
+        state = state.try_action(TetAction::HardDrop, 50).unwrap();
+        let action = input.on_new_state(&state, settings, 50).action;
+        assert_eq!(action, Some(TetAction::Hold));
+        state = state.try_action(TetAction::Hold, 50).unwrap();
+        assert_eq!(state.last_action, TetAction::Hold);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn handling_settings() {
+        let mut settings = GameSettings::default();
+        settings.input.autorepeat_delay_after = Duration::ZERO;
+        settings.input.das_cut_delay = Duration::from_millis(50);
+        settings.input.instant_soft_drop = true;
+        settings.input.prevent_accidental_hard_drop = true;
+        let mut input = GameInputManager::new();
+        let mut state = GameState::new(&[1; 32], 0);
+        input.on_new_state(&state, settings, 0);
+
+        let event = input.on_user_keyboard_event(
+            key_down(GameInputEventKey::SoftDrop),
+            settings,
+            &state,
+            0,
+        );
+        assert_eq!(event.action, Some(TetAction::SoftDropToFloor));
+
+        input.on_user_keyboard_event(
+            key_down(GameInputEventKey::MoveLeft),
+            settings,
+            &state,
+            0,
+        );
+        let event =
+            input.callback_after_wait(CallbackMoveType::RepeatMoveLeft, settings);
+        assert_eq!(event.action, Some(TetAction::MoveLeftToWall));
+
+        // the next piece locks on its own: the charged key waits for the DAS
+        // cut and a hard drop right away is ignored
+        state = state.try_action(TetAction::HardDrop, 10).unwrap();
+        state = state.try_action(TetAction::HardDrop, 10).unwrap();
+        state.last_action = TetAction::AutoSoftDrop;
+        let event = input.on_new_state(&state, settings, 100);
+        assert!(event.callback_tickets.contains(&CallbackTicket {
+            request_type: CallbackRequestType::SetCallback(
+                settings.input.das_cut_delay
+            ),
+            move_type: CallbackMoveType::RepeatMoveLeft,
+        }));
+        let event = input.on_user_keyboard_event(
+            key_down(GameInputEventKey::HardDrop),
+            settings,
+            &state,
+            120,
+        );
+        assert_eq!(event.action, None);
+
+        let mut key_up = key_down(GameInputEventKey::HardDrop);
+        key_up.event = GameInputEventType::KeyUp;
+        input.on_user_keyboard_event(key_up, settings, &state, 130);
+        let event = input.on_user_keyboard_event(
+            key_down(GameInputEventKey::HardDrop),
+            settings,
+            &state,
+            100 + ACCIDENTAL_HARD_DROP_WINDOW_MS,
+        );
+        assert_eq!(event.action, Some(TetAction::HardDrop));
+    }
+}
@@ -1,3 +1,10 @@
+mod solo_modes;
+
+pub use solo_modes::{
+    get_solo_mode_rule, get_solo_mode_rule_with_clock, solo_mode_is_complete,
+    BlitzRule, DigRule, RisingGarbageRule, SetupRule, SprintRule,
+};
+
 use crate::tet::GameState;
 
 #[async_trait::async_trait]
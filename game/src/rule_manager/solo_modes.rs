@@ -0,0 +1,450 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    api::game_match::GameMatchType,
+    tet::{GameOverReason, GameReplaySegment, GameState},
+    timestamp::{real_clock, ManualClock, SharedClock},
+};
+
+use super::RuleManager;
+
+/// Rules for the solo modes of a match type, if it has any.
+pub fn get_solo_mode_rule(
+    match_type: &GameMatchType,
+) -> Option<Arc<dyn RuleManager + 'static + Send + Sync>> {
+    get_solo_mode_rule_with_clock(match_type, real_clock())
+}
+
+/// Same as `get_solo_mode_rule`, the timed modes follow `clock`.
+pub fn get_solo_mode_rule_with_clock(
+    match_type: &GameMatchType,
+    clock: SharedClock,
+) -> Option<Arc<dyn RuleManager + 'static + Send + Sync>> {
+    match match_type {
+        GameMatchType::_40lines => Some(Arc::new(SprintRule { lines: 40 })),
+        GameMatchType::Blitz => Some(Arc::new(BlitzRule {
+            duration_ms: 120_000,
+            clock,
+        })),
+        GameMatchType::CheeseRace => Some(Arc::new(DigRule)),
+        GameMatchType::Survival => Some(Arc::new(RisingGarbageRule {
+            interval_ms: 3000,
+            lines: 1,
+            clock,
+        })),
+        GameMatchType::Setup(_) => {
+            let setup = GameMatchType::get_match_setup(&Some(match_type.clone()))?;
+            let pieces = setup.queue().len() as u32;
+            (pieces > 0).then(|| Arc::new(SetupRule { pieces }) as Arc<_>)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the solo mode of `match_type` agrees `state` is complete at
+/// `now_ms`, without waiting. The player's clock may run up to
+/// `tolerance_ms` ahead of ours, so an honest Blitz finish that arrives a
+/// little early still counts.
+pub fn solo_mode_is_complete(
+    match_type: &GameMatchType,
+    state: GameState,
+    now_ms: i64,
+    tolerance_ms: i64,
+) -> bool {
+    use futures_util::FutureExt;
+    let clock = Arc::new(ManualClock::new(now_ms + tolerance_ms));
+    get_solo_mode_rule_with_clock(match_type, clock)
+        .and_then(|rule| rule.accept_state(state).now_or_never())
+        .and_then(|r| r.ok().flatten())
+        .is_some_and(|s| {
+            s.game_over_reason == Some(GameOverReason::Complete)
+                && s.last_segment
+                    == GameReplaySegment::GameOver(GameOverReason::Complete)
+        })
+}
+
+/// Sprint: the game is complete as soon as `lines` lines are cleared, the
+/// result is `GameState::play_time_ms`.
+pub struct SprintRule {
+    pub lines: u16,
+}
+
+#[async_trait::async_trait]
+impl RuleManager for SprintRule {
+    async fn accept_state(
+        &self,
+        mut state: GameState,
+    ) -> anyhow::Result<Option<GameState>> {
+        if state.game_over() || state.total_lines < self.lines {
+            return Ok(None);
+        }
+        state.finish(GameOverReason::Complete);
+        Ok(Some(state))
+    }
+}
+
+/// Blitz: the game is complete after `duration_ms`, the result is the
+/// score.
+pub struct BlitzRule {
+    pub duration_ms: i64,
+    pub clock: SharedClock,
+}
+
+#[async_trait::async_trait]
+impl RuleManager for BlitzRule {
+    async fn accept_state(
+        &self,
+        mut state: GameState,
+    ) -> anyhow::Result<Option<GameState>> {
+        if state.game_over() {
+            return Ok(None);
+        }
+        let end_time = state.start_time + self.duration_ms;
+        let wait_ms = end_time - self.clock.now_ms();
+        if wait_ms > 0 && state.last_event_time < end_time {
+            self.clock
+                .sleep(Duration::from_millis(wait_ms as u64))
+                .await;
+        }
+        state.finish(GameOverReason::Complete);
+        Ok(Some(state))
+    }
+}
+
+/// Cheese race: the game is complete once the garbage the board started
+/// with (`GarbageRules::initial_rows`) is dug out, the result is
+/// `GameState::play_time_ms`.
+pub struct DigRule;
+
+#[async_trait::async_trait]
+impl RuleManager for DigRule {
+    async fn accept_state(
+        &self,
+        mut state: GameState,
+    ) -> anyhow::Result<Option<GameState>> {
+        if state.game_over()
+            || state.rules.garbage.initial_rows == 0
+            || state.main_board.count_garbage_rows() > 0
+        {
+            return Ok(None);
+        }
+        state.finish(GameOverReason::Complete);
+        Ok(Some(state))
+    }
+}
+
+/// Survival: `lines` of garbage are received every `interval_ms` until the
+/// player is knocked out, the result is `GameState::play_time_ms`. The
+/// garbage goes through `garbage_recv` like attacks from an opponent, so
+/// replays rebuild it.
+pub struct RisingGarbageRule {
+    pub interval_ms: i64,
+    pub lines: u16,
+    pub clock: SharedClock,
+}
+
+impl RisingGarbageRule {
+    fn garbage_due(&self, start_time: i64, now: i64) -> u16 {
+        let ticks = (now - start_time).max(0) / self.interval_ms;
+        (ticks * self.lines as i64).min(u16::MAX as i64) as u16
+    }
+}
+
+#[async_trait::async_trait]
+impl RuleManager for RisingGarbageRule {
+    async fn accept_state(
+        &self,
+        mut state: GameState,
+    ) -> anyhow::Result<Option<GameState>> {
+        if state.game_over() {
+            return Ok(None);
+        }
+        let now = self.clock.now_ms();
+        let mut due = self.garbage_due(state.start_time, now);
+        if due <= state.garbage_recv {
+            let next_tick = now + self.interval_ms
+                - (now - state.start_time).rem_euclid(self.interval_ms);
+            self.clock
+                .sleep(Duration::from_millis((next_tick - now) as u64))
+                .await;
+            due = self.garbage_due(state.start_time, next_tick);
+        }
+        state.apply_raw_received_garbage(due);
+        Ok(Some(state))
+    }
+}
+
+/// Setup games: the game is complete once as many pieces as the setup
+/// queue holds are placed, the result is the score.
+pub struct SetupRule {
+    pub pieces: u32,
+}
+
+#[async_trait::async_trait]
+impl RuleManager for SetupRule {
+    async fn accept_state(
+        &self,
+        mut state: GameState,
+    ) -> anyhow::Result<Option<GameState>> {
+        if state.game_over() || state.stats.pieces < self.pieces {
+            return Ok(None);
+        }
+        state.finish(GameOverReason::Complete);
+        Ok(Some(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{wordpress_blog_bot::WordpressBlogBot, TetBot};
+    use crate::state_manager::GameStateManager;
+    use crate::tet::{
+        encode_fumen, FumenPage, FumenQuiz, GameRules, GameState, GarbageRules, Tet,
+        TetAction,
+    };
+    use crate::timestamp::{Clock, ManualClock};
+    use futures_util::FutureExt;
+    use std::future::Future;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn sprint_completes_on_line_goal() {
+        let rule = SprintRule { lines: 40 };
+        let mut state = GameState::new(&[0; 32], 0);
+        state = state.try_action(TetAction::HardDrop, 1234).unwrap();
+        state.total_lines = 39;
+        let r = rule.accept_state(state).now_or_never().unwrap().unwrap();
+        assert!(r.is_none());
+
+        state.total_lines = 40;
+        let r = rule.accept_state(state).now_or_never().unwrap().unwrap();
+        let done = r.unwrap();
+        assert_eq!(done.game_over_reason, Some(GameOverReason::Complete));
+        assert_eq!(done.play_time_ms(), 1234);
+    }
+
+    /// Plays the moves of a bot, one action per state, on a manual clock.
+    /// Stops playing after `actions_left` actions.
+    struct BotPlayerRule {
+        clock: Arc<ManualClock>,
+        moves: std::sync::Mutex<Vec<TetAction>>,
+        actions_left: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl RuleManager for BotPlayerRule {
+        async fn accept_state(
+            &self,
+            state: GameState,
+        ) -> anyhow::Result<Option<GameState>> {
+            // a player is never instant, rules that are ready go first
+            let mut yielded = false;
+            futures_util::future::poll_fn(|cx| {
+                if yielded {
+                    return std::task::Poll::Ready(());
+                }
+                yielded = true;
+                cx.waker().wake_by_ref();
+                std::task::Poll::Pending
+            })
+            .await;
+            let played = self.actions_left.fetch_update(
+                std::sync::atomic::Ordering::SeqCst,
+                std::sync::atomic::Ordering::SeqCst,
+                |left| left.checked_sub(1),
+            );
+            if played.is_err() {
+                futures_util::future::pending::<()>().await;
+            }
+            let mut moves = self.moves.lock().unwrap();
+            loop {
+                if moves.is_empty() {
+                    *moves = WordpressBlogBot.choose_move(&state)?;
+                    moves.reverse();
+                }
+                let action = moves.pop().unwrap();
+                self.clock.advance(Duration::from_millis(50));
+                match state.try_action(action, self.clock.now_ms()) {
+                    Ok(next) => return Ok(Some(next)),
+                    Err(_) => moves.clear(),
+                }
+            }
+        }
+    }
+
+    /// A bot plays `match_type` through a `GameStateManager` with the solo
+    /// mode rule, until the game is over or the bot stops.
+    fn play_solo_mode(match_type: GameMatchType, max_actions: u32) -> GameState {
+        let clock = Arc::new(ManualClock::new(0));
+        let rules = GameMatchType::get_match_rules(&Some(match_type.clone()));
+        let mut manager = GameStateManager::new_with_rules(&[7; 32], 0, rules);
+        manager.set_clock(clock.clone());
+        let bot = Arc::new(BotPlayerRule {
+            clock: clock.clone(),
+            moves: Default::default(),
+            actions_left: max_actions.into(),
+        });
+        manager.add_rule("bot", bot.clone());
+        let solo_rule = get_solo_mode_rule_with_clock(&match_type, manager.clock());
+        manager.add_rule("solo_mode", solo_rule.unwrap());
+
+        // the bot wakes itself, so keep polling until it is out of actions
+        {
+            let mut main_loop = std::pin::pin!(manager.main_loop());
+            let mut cx =
+                std::task::Context::from_waker(futures_util::task::noop_waker_ref());
+            while main_loop.as_mut().poll(&mut cx).is_pending() {
+                if bot.actions_left.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+                    let _ = main_loop.as_mut().poll(&mut cx);
+                    break;
+                }
+            }
+        }
+        manager.get_state().now_or_never().unwrap()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn sprint_game_completes_in_state_manager() {
+        let state = play_solo_mode(GameMatchType::_40lines, 10_000);
+        assert_eq!(state.game_over_reason, Some(GameOverReason::Complete));
+        assert!(state.total_lines >= 40);
+        assert_eq!(state.play_time_ms(), state.last_event_time);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn cheese_game_completes_in_state_manager() {
+        let state = play_solo_mode(GameMatchType::CheeseRace, 10_000);
+        assert_eq!(state.game_over_reason, Some(GameOverReason::Complete));
+        assert_eq!(state.main_board.count_garbage_rows(), 0);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn survival_garbage_rises_in_state_manager() {
+        // 300 actions of 50ms, one line every 3 seconds
+        let state = play_solo_mode(GameMatchType::Survival, 300);
+        assert_eq!(state.game_over_reason, None);
+        assert_eq!(state.garbage_recv, 5);
+        assert!(state.garbage_applied > 0);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn blitz_ends_once_time_is_up() {
+        let clock = Arc::new(ManualClock::new(0));
+        let rule = BlitzRule {
+            duration_ms: 1000,
+            clock: clock.clone(),
+        };
+        let state = GameState::new(&[0; 32], 0);
+        let mut end = rule.accept_state(state);
+        assert!((&mut end).now_or_never().is_none());
+        clock.advance(Duration::from_millis(1000));
+        let r = end.now_or_never().unwrap().unwrap();
+        assert_eq!(r.unwrap().game_over_reason, Some(GameOverReason::Complete));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn blitz_completion_allows_a_clock_ahead() {
+        let state = GameState::new(&[0; 32], 1_000);
+        // our clock is 300ms behind the player's when the finish arrives
+        let now = 1_000 + 120_000 - 300;
+        assert!(!solo_mode_is_complete(&GameMatchType::Blitz, state, now, 0));
+        assert!(solo_mode_is_complete(
+            &GameMatchType::Blitz,
+            state,
+            now,
+            500
+        ));
+        assert!(!solo_mode_is_complete(
+            &GameMatchType::Blitz,
+            state,
+            now - 60_000,
+            500
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn dig_completes_when_garbage_is_gone() {
+        let rules = GameRules {
+            garbage: GarbageRules {
+                initial_rows: 10,
+                ..GarbageRules::cheese()
+            },
+            ..Default::default()
+        };
+        let mut state = GameState::new_with_rules(&[4; 32], 0, rules);
+        assert_eq!(state.main_board.count_garbage_rows(), 10);
+        // same seed, same cheese
+        let other = GameState::new_with_rules(&[4; 32], 99, rules);
+        assert_eq!(state.main_board.rows()[..10], other.main_board.rows()[..10]);
+
+        let r = DigRule.accept_state(state).now_or_never().unwrap().unwrap();
+        assert!(r.is_none());
+        for _ in 0..10 {
+            state.main_board.clear_line(0);
+        }
+        let r = DigRule.accept_state(state).now_or_never().unwrap().unwrap();
+        assert_eq!(r.unwrap().game_over_reason, Some(GameOverReason::Complete));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn rising_garbage_catches_up_with_the_clock() {
+        let clock = Arc::new(ManualClock::new(5500));
+        let rule = RisingGarbageRule {
+            interval_ms: 1000,
+            lines: 2,
+            clock: clock.clone(),
+        };
+        let state = GameState::new(&[0; 32], 0);
+        let r = rule.accept_state(state).now_or_never().unwrap().unwrap();
+        let state = r.unwrap();
+        assert_eq!(state.garbage_recv, 10);
+
+        // nothing new is due until the next tick
+        let mut next = rule.accept_state(state);
+        assert!((&mut next).now_or_never().is_none());
+        clock.advance(Duration::from_millis(500));
+        let state = next.now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(state.garbage_recv, 12);
+        assert_eq!(clock.now_ms(), 6000);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn setup_completes_when_queue_is_used() {
+        let page = FumenPage {
+            comment: FumenQuiz {
+                hold: None,
+                current: Some(Tet::O),
+                next: vec![Tet::O],
+            }
+            .to_comment(),
+            ..Default::default()
+        };
+        let match_type = GameMatchType::Setup(encode_fumen(&[page]));
+        let url = GameMatchType::to_url(&Some(match_type.clone()));
+        assert_eq!(
+            GameMatchType::from_url(&url).unwrap(),
+            Some(match_type.clone())
+        );
+
+        let setup = GameMatchType::get_match_setup(&Some(match_type.clone())).unwrap();
+        let rule = get_solo_mode_rule(&match_type).unwrap();
+        let mut state = GameState::from_setup(&setup, &[0; 32], 0, Default::default());
+        assert_eq!(state.current_pcs.unwrap().tet, Tet::O);
+        state = state.try_action(TetAction::HardDrop, 1).unwrap();
+        let r = rule.accept_state(state).now_or_never().unwrap().unwrap();
+        assert!(r.is_none());
+        state = state.try_action(TetAction::HardDrop, 2).unwrap();
+        let r = rule.accept_state(state).now_or_never().unwrap().unwrap();
+        assert_eq!(r.unwrap().game_over_reason, Some(GameOverReason::Complete));
+    }
+}
@@ -32,7 +32,28 @@ pub struct GameSettings {
 )]
 pub struct GameInputSettings {
     pub autorepeat_delay_initial: Duration,
+    /// ARR, zero shifts to the wall at once
     pub autorepeat_delay_after: Duration,
+    /// IRS: a rotate key held when a piece spawns rotates it right away
+    #[serde(default)]
+    pub initial_rotation: bool,
+    /// IHS: a hold key held when a piece spawns holds it right away
+    #[serde(default)]
+    pub initial_hold: bool,
+    /// DAS cut: a charged move key waits this long before shifting a new
+    /// piece, zero for no wait
+    #[serde(default)]
+    pub das_cut_delay: Duration,
+    /// soft drop repeats at the gravity divided by this factor, 0 uses the
+    /// repeat delays of the move keys
+    #[serde(default)]
+    pub soft_drop_factor: u16,
+    /// soft drop moves the piece to the floor at once
+    #[serde(default)]
+    pub instant_soft_drop: bool,
+    /// ignore hard drops right after a piece spawned by locking on its own
+    #[serde(default)]
+    pub prevent_accidental_hard_drop: bool,
 }
 
 impl Default for GameInputSettings {
@@ -40,6 +61,12 @@ impl Default for GameInputSettings {
         Self {
             autorepeat_delay_initial: Duration::from_millis(140),
             autorepeat_delay_after: Duration::from_millis(29),
+            initial_rotation: false,
+            initial_hold: false,
+            das_cut_delay: Duration::ZERO,
+            soft_drop_factor: 0,
+            instant_soft_drop: false,
+            prevent_accidental_hard_drop: false,
         }
     }
 }
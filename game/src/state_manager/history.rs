@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+use crate::tet::GameState;
+
+/// Practice mode history: one snapshot per piece, taken when the piece
+/// spawns. Undo goes back to the start of the previous piece.
+#[derive(Debug, Clone)]
+pub struct StateHistory {
+    snapshots: VecDeque<GameState>,
+    cursor: usize,
+    max_len: usize,
+}
+
+fn piece_id(state: &GameState) -> Option<u16> {
+    state.current_pcs.map(|p| p.id)
+}
+
+impl StateHistory {
+    pub fn new(initial: GameState, max_len: usize) -> Self {
+        Self {
+            snapshots: VecDeque::from([initial]),
+            cursor: 0,
+            max_len: max_len.max(1),
+        }
+    }
+
+    /// Record a new state; only the first state of each piece is kept.
+    /// Playing on after an undo drops the redo branch.
+    pub fn push(&mut self, state: GameState) {
+        if piece_id(&state) == piece_id(&self.snapshots[self.cursor]) {
+            return;
+        }
+        self.snapshots.truncate(self.cursor + 1);
+        self.snapshots.push_back(state);
+        if self.snapshots.len() > self.max_len {
+            self.snapshots.pop_front();
+        }
+        self.cursor = self.snapshots.len() - 1;
+    }
+
+    pub fn undo(&mut self) -> Option<GameState> {
+        self.cursor = self.cursor.checked_sub(1)?;
+        Some(self.snapshots[self.cursor])
+    }
+
+    pub fn redo(&mut self) -> Option<GameState> {
+        if self.cursor + 1 >= self.snapshots.len() {
+            return None;
+        }
+        self.cursor += 1;
+        Some(self.snapshots[self.cursor])
+    }
+
+    /// Go back (or forward) to the start of piece `id`.
+    pub fn jump_to_piece(&mut self, id: u16) -> Option<GameState> {
+        let idx = self
+            .snapshots
+            .iter()
+            .position(|s| piece_id(s) == Some(id))?;
+        self.cursor = idx;
+        Some(self.snapshots[idx])
+    }
+
+    /// Piece ids that can be jumped to, oldest first.
+    pub fn piece_ids(&self) -> Vec<u16> {
+        self.snapshots.iter().filter_map(piece_id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tet::TetAction;
+    use wasm_bindgen_test::*;
+
+    fn play(history: &mut StateHistory, mut state: GameState, n: usize) -> GameState {
+        for i in 0..n {
+            for action in [TetAction::MoveLeft, TetAction::HardDrop] {
+                state = state.try_action(action, i as i64).unwrap();
+                history.push(state);
+            }
+        }
+        state
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn undo_redo_and_jump() {
+        let state = GameState::new(&[1; 32], 0);
+        let mut history = StateHistory::new(state, 100);
+        let last = play(&mut history, state, 5);
+        assert_eq!(history.piece_ids(), vec![0, 1, 2, 3, 4, 5]);
+
+        let undone = history.undo().unwrap();
+        assert_eq!(undone.current_pcs.unwrap().id, 4);
+        assert_eq!(history.redo().unwrap(), last);
+        assert!(history.redo().is_none());
+
+        let first = history.jump_to_piece(0).unwrap();
+        assert_eq!(first, state);
+        assert!(history.undo().is_none());
+
+        // playing from the past drops the redo branch
+        play(&mut history, first, 1);
+        assert_eq!(history.piece_ids(), vec![0, 1]);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn history_is_bounded() {
+        let state = GameState::new(&[1; 32], 0);
+        let mut history = StateHistory::new(state, 3);
+        play(&mut history, state, 5);
+        assert_eq!(history.piece_ids(), vec![3, 4, 5]);
+    }
+}
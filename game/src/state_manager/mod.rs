@@ -1,14 +1,21 @@
+mod history;
+
+pub use history::StateHistory;
+
 use std::{future::Future, sync::Arc};
 
 use async_stream::stream;
-use futures_util::StreamExt;
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_util::{future::BoxFuture, FutureExt, StreamExt};
 use n0_future::{task::AbortOnDropHandle, Stream};
 use rand::{rng, Rng};
-use tokio::sync::{Notify, RwLock};
+use tokio::sync::{Mutex, Notify, RwLock};
 
 use crate::{
     rule_manager::RuleManager,
-    tet::{GameSeed, GameState},
+    settings::GameInputSettings,
+    tet::{FinesseTracker, GameRules, GameSeed, GameSetup, GameState},
+    timestamp::{real_clock, SharedClock},
 };
 
 #[derive(Clone)]
@@ -18,6 +25,12 @@ pub struct GameStateManager {
     rule_managers: Vec<(String, Arc<dyn RuleManager + 'static + Send + Sync>)>,
     loops: Vec<Arc<AbortOnDropHandle<anyhow::Result<()>>>>,
     obj_id: u64,
+    history: Option<Arc<RwLock<StateHistory>>>,
+    /// wakes up the main loop when the state is replaced from outside
+    restore_notify: Arc<Notify>,
+    every_state_tx: Arc<Mutex<Vec<UnboundedSender<GameState>>>>,
+    /// clock the rules of this game run on, the wall clock by default
+    clock: SharedClock,
 }
 
 impl PartialEq for GameStateManager {
@@ -31,7 +44,26 @@ impl GameStateManager {
         *self.state.read().await
     }
     pub fn new(game_seed: &GameSeed, start_time: i64) -> Self {
-        let state = GameState::new(game_seed, start_time);
+        Self::new_with_rules(game_seed, start_time, GameRules::default())
+    }
+    pub fn new_with_rules(
+        game_seed: &GameSeed,
+        start_time: i64,
+        rules: GameRules,
+    ) -> Self {
+        Self::new_with_setup(game_seed, start_time, rules, None)
+    }
+    /// Game starting from `setup`, or from an empty board without one.
+    pub fn new_with_setup(
+        game_seed: &GameSeed,
+        start_time: i64,
+        rules: GameRules,
+        setup: Option<GameSetup>,
+    ) -> Self {
+        let state = match setup {
+            Some(setup) => GameState::from_setup(&setup, game_seed, start_time, rules),
+            None => GameState::new_with_rules(game_seed, start_time, rules),
+        };
         let id: u64 = rng().random();
         tracing::info!("INIT GAME MANAGER {id}");
 
@@ -41,9 +73,94 @@ impl GameStateManager {
             rule_managers: vec![],
             loops: vec![],
             obj_id: id,
+            history: None,
+            restore_notify: Arc::new(Notify::new()),
+            every_state_tx: Arc::new(Mutex::new(vec![])),
+            clock: real_clock(),
+        }
+    }
+
+    /// Practice game: keeps the last `max_history` pieces for undo/redo.
+    /// Practice games are unranked and must never be sent to the server.
+    pub fn new_practice(
+        game_seed: &GameSeed,
+        start_time: i64,
+        rules: GameRules,
+        max_history: usize,
+    ) -> Self {
+        let rules = GameRules {
+            practice: true,
+            ..rules
+        };
+        let mut manager = Self::new_with_rules(game_seed, start_time, rules);
+        let state = GameState::new_with_rules(game_seed, start_time, rules);
+        manager.history =
+            Some(Arc::new(RwLock::new(StateHistory::new(state, max_history))));
+        manager
+    }
+
+    pub fn is_ranked(&self) -> bool {
+        self.history.is_none()
+    }
+
+    /// Go back to the start of the previous piece.
+    pub async fn undo(&self) -> Option<GameState> {
+        let state = self.history.as_ref()?.write().await.undo()?;
+        self.restore(state).await;
+        Some(state)
+    }
+
+    pub async fn redo(&self) -> Option<GameState> {
+        let state = self.history.as_ref()?.write().await.redo()?;
+        self.restore(state).await;
+        Some(state)
+    }
+
+    pub async fn jump_to_piece(&self, piece_id: u16) -> Option<GameState> {
+        let state = self
+            .history
+            .as_ref()?
+            .write()
+            .await
+            .jump_to_piece(piece_id)?;
+        self.restore(state).await;
+        Some(state)
+    }
+
+    /// Piece ids that `jump_to_piece` accepts, empty outside practice.
+    pub async fn history_piece_ids(&self) -> Vec<u16> {
+        match &self.history {
+            Some(h) => h.read().await.piece_ids(),
+            None => vec![],
         }
     }
 
+    async fn restore(&self, state: GameState) {
+        {
+            *self.state.write().await = state;
+        }
+        self.restore_notify.notify_one();
+        self.notify.notify_one();
+        self.notify.notify_waiters();
+    }
+
+    /// Set before adding the rules, they get their clock from `clock()`.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+    /// Judge finesse with the player's DAS instead of the default one. Set
+    /// before the game starts.
+    pub fn set_input_settings(&self, input: &GameInputSettings) {
+        let das_ms = FinesseTracker::new(input).das_ms();
+        match self.state.try_write() {
+            Ok(mut state) => state.finesse.set_das_ms(das_ms),
+            Err(_) => tracing::warn!("game already running, finesse keeps its DAS"),
+        }
+    }
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
     pub fn add_rule(
         &mut self,
         name: &str,
@@ -75,15 +192,25 @@ impl GameStateManager {
         self.notify.notify_waiters();
         tracing::info!("GameManager(): main_loop() started");
 
-        while !current_state.game_over() {
-            let mut fut = n0_future::FuturesUnordered::new();
+        // a practice game can be brought back to life with undo
+        while !current_state.game_over() || self.history.is_some() {
+            let mut fut = n0_future::FuturesUnordered::<
+                BoxFuture<'_, (String, anyhow::Result<Option<GameState>>)>,
+            >::new();
             let state2 = current_state;
             for manager in self.rule_managers.iter() {
                 let manager = manager.clone();
                 let next = async move {
                     (manager.0.clone(), manager.1.accept_state(state2).await)
                 };
-                fut.push(next);
+                fut.push(next.boxed());
+            }
+            if self.history.is_some() {
+                let restored = async move {
+                    self.restore_notify.notified().await;
+                    ("restore".to_string(), Ok(Some(self.get_state().await)))
+                };
+                fut.push(restored.boxed());
             }
             while let Some((rule_name, result)) = fut.next().await {
                 match result {
@@ -109,12 +236,28 @@ impl GameStateManager {
 
     async fn _set_state_and_notify(&self, new_state: GameState) {
         {
+            let mut senders = self.every_state_tx.lock().await;
             *self.state.write().await = new_state;
+            senders.retain(|tx| tx.unbounded_send(new_state).is_ok());
+        }
+        if let Some(history) = &self.history {
+            history.write().await.push(new_state);
         }
         self.notify.notify_one();
         self.notify.notify_waiters();
     }
 
+    /// Every state of the game in order, starting with the current one.
+    /// Unlike `read_state_stream` nothing is skipped for slow readers, so
+    /// the server can re-simulate the game from it.
+    pub async fn read_every_state_stream(&self) -> UnboundedReceiver<GameState> {
+        let (tx, rx) = unbounded();
+        let mut senders = self.every_state_tx.lock().await;
+        let _ = tx.unbounded_send(*self.state.read().await);
+        senders.push(tx);
+        rx
+    }
+
     pub fn read_state_stream(&self) -> impl Stream<Item = GameState> + Send + 'static {
         let state_arc = self.state.clone();
         let notify_arc = self.notify.clone();
@@ -0,0 +1,109 @@
+use once_cell::sync::Lazy;
+
+use super::{rot::RotState, rot_system::RotationSystemKind, tetpcs::Tet};
+
+/// Occupancy of a piece in its box: bit `x` of `rows[y]` is the cell at
+/// `(pos.0 + y, pos.1 + x)`, rows from bottom to top like `Shape`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+pub struct PieceMask {
+    pub rows: [u16; 4],
+}
+
+impl PieceMask {
+    /// Row `y` of the piece moved to column `x`, `None` if a cell falls
+    /// outside of a board `width` columns wide.
+    #[inline(always)]
+    pub fn row_at(&self, y: usize, x: i8, width: usize) -> Option<u16> {
+        let row = self.rows[y];
+        let shifted = if x >= 0 {
+            let shifted = (row as u32) << x;
+            if shifted >> width != 0 {
+                return None;
+            }
+            shifted as u16
+        } else {
+            if row & ((1 << -x) - 1) != 0 {
+                return None;
+            }
+            row >> -x
+        };
+        Some(shifted)
+    }
+
+    /// Rows of the piece that have cells, with their offset from `pos.0`.
+    #[inline(always)]
+    pub fn filled_rows(&self) -> impl Iterator<Item = (usize, u16)> + '_ {
+        self.rows
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, row)| *row != 0)
+    }
+}
+
+/// Masks for every rotation system, piece and rotation state, built once
+/// from the shapes of the rotation systems so the two never disagree.
+static PIECE_MASKS: Lazy<Vec<[[PieceMask; 4]; 7]>> = Lazy::new(|| {
+    RotationSystemKind::all()
+        .into_iter()
+        .map(|k| {
+            let mut masks = [[PieceMask::default(); 4]; 7];
+            for tet in Tet::all() {
+                for rs in [RotState::R0, RotState::R1, RotState::R2, RotState::R3] {
+                    let mask = &mut masks[tet as usize][rs as usize];
+                    for (y, row) in k.get().shape(tet, rs).iter().enumerate() {
+                        for (x, filled) in row.iter().enumerate() {
+                            if *filled {
+                                mask.rows[y] |= 1 << x;
+                            }
+                        }
+                    }
+                }
+            }
+            masks
+        })
+        .collect()
+});
+
+#[inline(always)]
+pub fn piece_mask(rot_system: RotationSystemKind, tet: Tet, rs: RotState) -> PieceMask {
+    PIECE_MASKS[rot_system as usize][tet as usize][rs as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn masks_match_shapes() {
+        for k in RotationSystemKind::all() {
+            for tet in Tet::all() {
+                for rs in [RotState::R0, RotState::R1, RotState::R2, RotState::R3] {
+                    let mask = piece_mask(k, tet, rs);
+                    let shape = tet.shape(k, rs);
+                    for y in 0..4 {
+                        for x in 0..4 {
+                            let filled =
+                                shape.get(y).and_then(|r| r.get(x)) == Some(&true);
+                            assert_eq!(mask.rows[y] & (1 << x) != 0, filled);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn rows_outside_the_board() {
+        let mask = PieceMask {
+            rows: [0b0110, 0, 0, 0],
+        };
+        assert_eq!(mask.row_at(0, -1, 10), Some(0b0011));
+        assert_eq!(mask.row_at(0, -2, 10), None);
+        assert_eq!(mask.row_at(0, 7, 10), Some(0b0110 << 7));
+        assert_eq!(mask.row_at(0, 8, 10), None);
+    }
+}
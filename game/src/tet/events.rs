@@ -0,0 +1,167 @@
+use super::{game_state::GameOverReason, spin::SpinKind, tetpcs::Tet};
+
+/// Something that happened during one state transition. Sounds, popups and
+/// achievements read these instead of comparing two states.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum GameEvent {
+    PieceLocked {
+        tet: Tet,
+    },
+    Hold {
+        tet: Tet,
+    },
+    LineClear {
+        lines: u16,
+    },
+    /// also sent for spins that clear nothing
+    Spin {
+        kind: SpinKind,
+        lines: u16,
+    },
+    /// `chain` is 1 for the first clear that continues the chain
+    BackToBack {
+        chain: u16,
+    },
+    /// `count` is 1 for the second clear in a row
+    Combo {
+        count: u16,
+    },
+    PerfectClear,
+    /// garbage sent to opponents, after cancelling
+    AttackSent {
+        lines: u16,
+    },
+    GarbageCancelled {
+        lines: u16,
+    },
+    GarbageApplied {
+        lines: u16,
+    },
+    LevelUp {
+        level: u16,
+    },
+    GameOver(GameOverReason),
+    /// garbage sent by an opponent, before it is queued
+    GarbageReceived {
+        lines: u16,
+    },
+}
+
+const GAME_EVENTS_LEN: usize = 16;
+
+/// Events of the last transition, kept in a fixed array so `GameState`
+/// stays `Copy`. They are not serialized, replays produce them again.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct GameEventList {
+    events: [Option<GameEvent>; GAME_EVENTS_LEN],
+    len: u8,
+}
+
+impl Default for GameEventList {
+    fn default() -> Self {
+        Self {
+            events: [None; GAME_EVENTS_LEN],
+            len: 0,
+        }
+    }
+}
+
+impl GameEventList {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Events past the capacity are dropped, one action can not produce
+    /// that many.
+    pub fn push(&mut self, event: GameEvent) {
+        if self.len as usize == GAME_EVENTS_LEN {
+            tracing::warn!("game event list full, dropping {:?}", event);
+            return;
+        }
+        self.events[self.len as usize] = Some(event);
+        self.len += 1;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = GameEvent> + '_ {
+        self.events[..self.len as usize].iter().flatten().copied()
+    }
+
+    pub fn to_vec(&self) -> Vec<GameEvent> {
+        self.iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{CellValue, GameState, TetAction};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn hard_drop_and_hold_events() {
+        let mut state = GameState::new(&[0; 32], 0);
+        state.set_next_pcs(vec![Tet::O; 14]);
+        let (state, events) = state.try_action_with_events(TetAction::Hold, 0).unwrap();
+        assert_eq!(events, vec![GameEvent::Hold { tet: Tet::O }]);
+
+        let (state, events) = state
+            .try_action_with_events(TetAction::MoveLeft, 10)
+            .unwrap();
+        assert!(events.is_empty());
+
+        let (_, events) = state
+            .try_action_with_events(TetAction::HardDrop, 20)
+            .unwrap();
+        assert_eq!(events, vec![GameEvent::PieceLocked { tet: Tet::O }]);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn line_clear_events() {
+        let mut state = GameState::new(&[0; 32], 0);
+        state.set_next_pcs(vec![Tet::I; 14]);
+        state = state.try_action(TetAction::Hold, 0).unwrap();
+        // the flat I lands on columns 3 to 6
+        for x in [0, 1, 2, 7, 8, 9] {
+            state.main_board.set_cell(0, x, CellValue::Garbage);
+        }
+        let (state, events) = state
+            .try_action_with_events(TetAction::HardDrop, 10)
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::PieceLocked { tet: Tet::I },
+                GameEvent::LineClear { lines: 1 },
+                GameEvent::PerfectClear,
+                GameEvent::AttackSent { lines: 10 },
+            ]
+        );
+        assert_eq!(state.events.to_vec(), events);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn events_survive_received_garbage() {
+        let state = GameState::new(&[0; 32], 0);
+        let (mut state, events) = state
+            .try_action_with_events(TetAction::HardDrop, 10)
+            .unwrap();
+        state.apply_raw_received_garbage(3);
+        // the same total again is not new garbage
+        state.apply_raw_received_garbage(3);
+        let mut expected = events;
+        expected.push(GameEvent::GarbageReceived { lines: 3 });
+        assert_eq!(state.events.to_vec(), expected);
+
+        let (_, events) = state
+            .try_action_with_events(TetAction::MoveLeft, 20)
+            .unwrap();
+        assert!(!events.contains(&GameEvent::GarbageReceived { lines: 3 }));
+    }
+}
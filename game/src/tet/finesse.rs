@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::GameInputSettings;
+
+use super::{
+    game_state::{CurrentPcsInfo, GameState},
+    matrix::BoardMatrix,
+    rot::{RotDirection, RotState},
+    rot_system::RotationSystemKind,
+    tetpcs::{Tet, TetAction},
+};
+
+/// One key press as counted by finesse: a tap, a rotation, or a move held
+/// until the piece hits the wall.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum FinesseKey {
+    Left,
+    Right,
+    DasLeft,
+    DasRight,
+    RotateLeft,
+    RotateRight,
+    Rotate180,
+}
+
+impl FinesseKey {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Left,
+            Self::Right,
+            Self::DasLeft,
+            Self::DasRight,
+            Self::RotateLeft,
+            Self::RotateRight,
+            Self::Rotate180,
+        ]
+    }
+}
+
+fn fits(board: &BoardMatrix, pcs: &CurrentPcsInfo) -> bool {
+    let mut board = *board;
+    board.spawn_piece(pcs).is_ok()
+}
+
+fn shifted(
+    board: &BoardMatrix,
+    pcs: &CurrentPcsInfo,
+    dx: i8,
+) -> Option<CurrentPcsInfo> {
+    let mut moved = *pcs;
+    moved.pos.1 += dx;
+    fits(board, &moved).then_some(moved)
+}
+
+fn rotated(
+    board: &BoardMatrix,
+    pcs: &CurrentPcsInfo,
+    rot: RotDirection,
+) -> Option<CurrentPcsInfo> {
+    let after = pcs.rs.rotate(rot);
+    let kicks = pcs.rot_system.get().kicks(pcs.rs, after, pcs.tet);
+    kicks.into_iter().find_map(|(x, y)| {
+        let mut moved = *pcs;
+        moved.rs = after;
+        moved.pos.0 += y;
+        moved.pos.1 += x;
+        fits(board, &moved).then_some(moved)
+    })
+}
+
+fn press(
+    board: &BoardMatrix,
+    pcs: &CurrentPcsInfo,
+    key: FinesseKey,
+) -> Option<CurrentPcsInfo> {
+    let das = |dx: i8| {
+        let mut p = shifted(board, pcs, dx)?;
+        while let Some(next) = shifted(board, &p, dx) {
+            p = next;
+        }
+        Some(p)
+    };
+    match key {
+        FinesseKey::Left => shifted(board, pcs, -1),
+        FinesseKey::Right => shifted(board, pcs, 1),
+        FinesseKey::DasLeft => das(-1),
+        FinesseKey::DasRight => das(1),
+        FinesseKey::RotateLeft => rotated(board, pcs, RotDirection::Left),
+        FinesseKey::RotateRight => rotated(board, pcs, RotDirection::Right),
+        FinesseKey::Rotate180 => rotated(board, pcs, RotDirection::Half),
+    }
+}
+
+/// Cells of the piece, shifted down to the floor. Placements with the same
+/// footprint are the same for finesse, e.g. the two flat I rotations.
+fn footprint(pcs: &CurrentPcsInfo) -> Vec<(i8, i8)> {
+    let shape = pcs.tet.shape(pcs.rot_system, pcs.rs);
+    let mut cells = vec![];
+    for (y, row) in shape.iter().enumerate() {
+        for (x, filled) in row.iter().enumerate() {
+            if *filled {
+                cells.push((pcs.pos.0 + y as i8, pcs.pos.1 + x as i8));
+            }
+        }
+    }
+    let min_y = cells.iter().map(|c| c.0).min().unwrap_or(0);
+    let mut cells: Vec<_> = cells.into_iter().map(|(y, x)| (y - min_y, x)).collect();
+    cells.sort();
+    cells
+}
+
+fn spawn_on_empty(rot_system: RotationSystemKind, tet: Tet) -> CurrentPcsInfo {
+    CurrentPcsInfo {
+        pos: BoardMatrix::<40, 10>::empty().spawn_pos(tet, rot_system),
+        tet,
+        rs: RotState::R0,
+        id: 0,
+        rot_system,
+    }
+}
+
+/// Shortest key sequence to every footprint a freshly spawned `tet` can
+/// reach on an empty board.
+fn reachable_placements(
+    rot_system: RotationSystemKind,
+    tet: Tet,
+) -> HashMap<Vec<(i8, i8)>, Vec<FinesseKey>> {
+    let board = BoardMatrix::empty();
+    let spawn = spawn_on_empty(rot_system, tet);
+    let mut placements = HashMap::new();
+    let mut seen = HashSet::from([(spawn.pos, spawn.rs)]);
+    let mut queue = VecDeque::from([(spawn, vec![])]);
+    while let Some((pcs, keys)) = queue.pop_front() {
+        for key in FinesseKey::all() {
+            let Some(next) = press(&board, &pcs, key) else {
+                continue;
+            };
+            if seen.insert((next.pos, next.rs)) {
+                let mut next_keys = keys.clone();
+                next_keys.push(key);
+                queue.push_back((next, next_keys));
+            }
+        }
+        placements.entry(footprint(&pcs)).or_insert(keys);
+    }
+    placements
+}
+
+/// Shortest key sequence that brings a freshly spawned `tet` to the
+/// rotation and column of `target` on an empty board, `None` if it can not
+/// get there.
+pub fn minimal_keys(
+    rot_system: RotationSystemKind,
+    tet: Tet,
+    target_rs: RotState,
+    target_x: i8,
+) -> Option<Vec<FinesseKey>> {
+    let mut target = spawn_on_empty(rot_system, tet);
+    target.rs = target_rs;
+    target.pos.1 = target_x;
+    reachable_placements(rot_system, tet).remove(&footprint(&target))
+}
+
+/// Columns of the piece box that can hold a placement on the board.
+const PLACEMENT_COLUMNS: std::ops::Range<i8> = -4..12;
+
+/// Length of `minimal_keys` for every placement, so that judging a locked
+/// piece is a lookup.
+static MINIMAL_KEY_COUNTS: Lazy<HashMap<(RotationSystemKind, Tet, RotState, i8), u32>> =
+    Lazy::new(|| {
+        let mut h = HashMap::new();
+        for k in RotationSystemKind::all() {
+            for t in Tet::all() {
+                let placements = reachable_placements(k, t);
+                for r in [RotState::R0, RotState::R1, RotState::R2, RotState::R3] {
+                    for x in PLACEMENT_COLUMNS {
+                        let mut target = spawn_on_empty(k, t);
+                        target.rs = r;
+                        target.pos.1 = x;
+                        if let Some(keys) = placements.get(&footprint(&target)) {
+                            h.insert((k, t, r, x), keys.len() as u32);
+                        }
+                    }
+                }
+            }
+        }
+        h
+    });
+
+/// Counts the keys used for every piece and compares them with
+/// `minimal_keys` when the piece locks. Moves in the same direction closer
+/// together than the DAS delay count as a single held key. Pieces that
+/// were soft dropped are not judged, tucks and spins need the extra keys.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FinesseTracker {
+    pub placements: u32,
+    pub faults: u32,
+    /// keys used above the minimum, over all faults
+    pub extra_keys: u32,
+    das_ms: i64,
+    keys: u32,
+    soft_dropped: bool,
+    last_move: Option<(TetAction, i64)>,
+}
+
+impl FinesseTracker {
+    pub fn new(input: &GameInputSettings) -> Self {
+        Self {
+            das_ms: input.autorepeat_delay_initial.as_millis() as i64,
+            ..Default::default()
+        }
+    }
+
+    /// Tracker kept by `GameState` for `GameStats`, with the default DAS
+    /// until the player's handling is set with `set_das_ms`.
+    pub fn for_game_stats() -> Self {
+        Self::new(&GameInputSettings::default())
+    }
+
+    /// DAS delay that tells held move keys from taps.
+    pub fn das_ms(&self) -> i64 {
+        self.das_ms
+    }
+
+    pub fn set_das_ms(&mut self, das_ms: i64) {
+        self.das_ms = das_ms;
+    }
+
+    /// Feed two consecutive states of the same game.
+    pub fn on_new_state(&mut self, prev: &GameState, next: &GameState) {
+        if next.total_moves == prev.total_moves {
+            // not a player action, e.g. received garbage
+            return;
+        }
+        let action = next.last_action;
+        let t = next.last_event_time;
+        let (Some(before), after) = (prev.current_pcs, next.current_pcs) else {
+            return;
+        };
+        let locked = after.map(|p| p.id) != Some(before.id);
+        match action {
+            TetAction::Hold => {
+                self.start_piece();
+                return;
+            }
+            TetAction::HardDrop | TetAction::AutoSoftDrop if locked => {
+                self.judge(&before);
+                self.start_piece();
+                return;
+            }
+            _ if locked => {
+                // the lock delay ran out just before this action, which
+                // belongs to the next piece
+                self.judge(&before);
+                self.start_piece();
+            }
+            _ => {}
+        }
+        self.count_key(action, t);
+    }
+
+    fn count_key(&mut self, action: TetAction, t: i64) {
+        // a wall shift is the end of a held move key
+        let action = match action {
+            TetAction::MoveLeftToWall => TetAction::MoveLeft,
+            TetAction::MoveRightToWall => TetAction::MoveRight,
+            _ => action,
+        };
+        match action {
+            TetAction::MoveLeft | TetAction::MoveRight => {
+                let held = self.last_move.is_some_and(|(last, last_t)| {
+                    last == action && t - last_t <= self.das_ms
+                });
+                if !held {
+                    self.keys += 1;
+                }
+                self.last_move = Some((action, t));
+                return;
+            }
+            TetAction::RotateLeft | TetAction::RotateRight | TetAction::Rotate180 => {
+                self.keys += 1;
+            }
+            TetAction::UserSoftDrop | TetAction::SoftDropToFloor => {
+                self.soft_dropped = true;
+            }
+            // gravity does not interrupt a held key
+            TetAction::AutoSoftDrop | TetAction::Nothing => return,
+            _ => {}
+        }
+        self.last_move = None;
+    }
+
+    fn judge(&mut self, placed: &CurrentPcsInfo) {
+        if self.soft_dropped {
+            return;
+        }
+        let key = (placed.rot_system, placed.tet, placed.rs, placed.pos.1);
+        let Some(&minimal) = MINIMAL_KEY_COUNTS.get(&key) else {
+            return;
+        };
+        self.placements += 1;
+        if self.keys > minimal {
+            self.faults += 1;
+            self.extra_keys += self.keys - minimal;
+        }
+    }
+
+    fn start_piece(&mut self) {
+        self.keys = 0;
+        self.soft_dropped = false;
+        self.last_move = None;
+    }
+
+    /// Finesse of a whole game, e.g. from `segments_to_states`.
+    pub fn from_states(states: &[GameState], input: &GameInputSettings) -> Self {
+        let mut tracker = Self::new(input);
+        for w in states.windows(2) {
+            tracker.on_new_state(&w[0], &w[1]);
+        }
+        tracker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn keys(tet: Tet, rs: RotState, x: i8) -> usize {
+        minimal_keys(RotationSystemKind::Srs, tet, rs, x)
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn minimal_keys_for_srs() {
+        // O spawns at column 4
+        let o: Vec<_> = (0..9).map(|x| keys(Tet::O, RotState::R0, x)).collect();
+        assert_eq!(o, vec![1, 2, 2, 1, 0, 1, 2, 2, 1]);
+        // vertical I against the left wall: rotate and DAS
+        assert_eq!(keys(Tet::I, RotState::R1, -2), 2);
+        // both flat I rotations are the same placement
+        assert_eq!(keys(Tet::I, RotState::R2, 3), 0);
+        assert_eq!(keys(Tet::T, RotState::R2, 3), 1);
+    }
+
+    fn play(actions: &[(TetAction, i64)]) -> FinesseTracker {
+        let mut state = GameState::new(&[0; 32], 0);
+        state.set_next_pcs(vec![Tet::O; 14]);
+        state = state.try_action(TetAction::HardDrop, 0).unwrap();
+        let mut states = vec![state];
+        for (action, t) in actions {
+            state = state.try_action(*action, *t).unwrap();
+            states.push(state);
+        }
+        FinesseTracker::from_states(&states, &GameInputSettings::default())
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn key_counts_match_minimal_keys() {
+        for k in RotationSystemKind::all() {
+            for t in Tet::all() {
+                for x in PLACEMENT_COLUMNS {
+                    let count = MINIMAL_KEY_COUNTS.get(&(k, t, RotState::R1, x));
+                    let keys = minimal_keys(k, t, RotState::R1, x);
+                    assert_eq!(count.copied(), keys.map(|v| v.len() as u32));
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn tapping_to_the_wall_is_a_fault() {
+        let left = TetAction::MoveLeft;
+        let taps = play(&[
+            (left, 0),
+            (left, 300),
+            (left, 600),
+            (left, 900),
+            (TetAction::HardDrop, 1000),
+        ]);
+        assert_eq!((taps.placements, taps.faults, taps.extra_keys), (1, 1, 3));
+
+        let held = play(&[
+            (left, 0),
+            (left, 140),
+            (left, 170),
+            (left, 200),
+            (TetAction::HardDrop, 300),
+        ]);
+        assert_eq!((held.placements, held.faults), (1, 0));
+    }
+}
@@ -0,0 +1,733 @@
+use anyhow::Context;
+
+use super::{
+    game_state::GameState,
+    matrix::{BoardMatrix, CellValue},
+    rot::RotState,
+    tetpcs::Tet,
+};
+
+pub const FUMEN_WIDTH: usize = 10;
+/// Visible rows of a fumen field; there is one more garbage row under them.
+pub const FUMEN_HEIGHT: usize = 23;
+const FIELD_BLOCKS: usize = (FUMEN_HEIGHT + 1) * FUMEN_WIDTH;
+const EMPTY_DIFF: u32 = 8;
+
+const TABLE: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// printable ASCII, comments are `escape()`d before encoding
+const COMMENT_TABLE_LEN: u32 = 96;
+const MAX_COMMENT_LEN: usize = 4095;
+
+/// A piece on a fumen page, in the coordinates of the `tetris-fumen`
+/// library: `(x, y)` is the rotation center, y=0 is the bottom row.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct FumenPiece {
+    pub tet: Tet,
+    pub rs: RotState,
+    pub x: i8,
+    pub y: i8,
+}
+
+impl FumenPiece {
+    /// Cells of the piece as `(y, x)`.
+    pub fn cells(&self) -> [(i8, i8); 4] {
+        let base = match self.tet {
+            Tet::I => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+            Tet::T => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+            Tet::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Tet::L => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+            Tet::J => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+            Tet::S => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+            Tet::Z => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+        };
+        base.map(|(x, y)| {
+            let (x, y) = match self.rs {
+                RotState::R0 => (x, y),
+                RotState::R1 => (y, -x),
+                RotState::R2 => (-x, -y),
+                RotState::R3 => (-y, x),
+            };
+            (self.y + y, self.x + x)
+        })
+    }
+
+    /// Fumen stores some pieces off their rotation center, so that the
+    /// two rotations of I, S and Z that cover the same cells are the same.
+    fn center_offset(tet: Tet, rs: RotState) -> (i8, i8) {
+        match (tet, rs) {
+            (Tet::O, RotState::R3) => (1, -1),
+            (Tet::O, RotState::R2) => (1, 0),
+            (Tet::O, RotState::R0) => (0, -1),
+            (Tet::I, RotState::R2) => (1, 0),
+            (Tet::I, RotState::R3) => (0, -1),
+            (Tet::S, RotState::R0) => (0, -1),
+            (Tet::S, RotState::R1) => (-1, 0),
+            (Tet::Z, RotState::R0) => (0, -1),
+            (Tet::Z, RotState::R3) => (1, 0),
+            _ => (0, 0),
+        }
+    }
+
+    fn location(&self) -> u32 {
+        let (dx, dy) = Self::center_offset(self.tet, self.rs);
+        let (x, y) = (self.x - dx, self.y - dy);
+        ((FUMEN_HEIGHT as i32 - y as i32 - 1) * FUMEN_WIDTH as i32 + x as i32)
+            .clamp(0, FIELD_BLOCKS as i32 - 1) as u32
+    }
+
+    fn from_location(tet: Tet, rs: RotState, location: u32) -> Self {
+        let x = (location % FUMEN_WIDTH as u32) as i8;
+        let y = FUMEN_HEIGHT as i8 - (location / FUMEN_WIDTH as u32) as i8 - 1;
+        let (dx, dy) = Self::center_offset(tet, rs);
+        Self {
+            tet,
+            rs,
+            x: x + dx,
+            y: y + dy,
+        }
+    }
+}
+
+/// The quiz comment `#Q=[hold](current)next` that fumen uses for queues.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct FumenQuiz {
+    pub hold: Option<Tet>,
+    pub current: Option<Tet>,
+    pub next: Vec<Tet>,
+}
+
+impl FumenQuiz {
+    pub fn parse(comment: &str) -> Option<Self> {
+        let rest = comment.strip_prefix("#Q=[")?;
+        let (hold, rest) = rest.split_once("](")?;
+        let (current, rest) = rest.split_once(')')?;
+        let next = rest
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != ';')
+            .map(Tet::from_char)
+            .collect::<Option<Vec<_>>>()?;
+        let one = |s: &str| -> Option<Option<Tet>> {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (None, _) => Some(None),
+                (Some(c), None) => Some(Some(Tet::from_char(c)?)),
+                _ => None,
+            }
+        };
+        Some(Self {
+            hold: one(hold)?,
+            current: one(current)?,
+            next,
+        })
+    }
+
+    pub fn to_comment(&self) -> String {
+        let name = |t: Option<Tet>| t.map(|t| t.name().to_string()).unwrap_or_default();
+        let next: String = self.next.iter().map(|t| t.name()).collect();
+        format!("#Q=[{}]({}){}", name(self.hold), name(self.current), next)
+    }
+}
+
+/// One page of a fumen: the field before `piece` is placed, the piece and
+/// the comment. `lock` places the piece and clears lines for the next page,
+/// `rise` then pushes the garbage row into the field and `mirror` flips it.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct FumenPage {
+    /// `field[y][x]`, y=0 is the bottom row
+    pub field: [[CellValue; FUMEN_WIDTH]; FUMEN_HEIGHT],
+    pub garbage_row: [CellValue; FUMEN_WIDTH],
+    pub piece: Option<FumenPiece>,
+    pub comment: String,
+    pub lock: bool,
+    pub rise: bool,
+    pub mirror: bool,
+}
+
+impl Default for FumenPage {
+    fn default() -> Self {
+        Self {
+            field: [[CellValue::Empty; FUMEN_WIDTH]; FUMEN_HEIGHT],
+            garbage_row: [CellValue::Empty; FUMEN_WIDTH],
+            piece: None,
+            comment: String::new(),
+            lock: true,
+            rise: false,
+            mirror: false,
+        }
+    }
+}
+
+fn cell_code(cell: CellValue) -> u32 {
+    match cell {
+        CellValue::Empty | CellValue::Ghost => 0,
+        CellValue::Piece(Tet::I) => 1,
+        CellValue::Piece(Tet::L) => 2,
+        CellValue::Piece(Tet::O) => 3,
+        CellValue::Piece(Tet::Z) => 4,
+        CellValue::Piece(Tet::T) => 5,
+        CellValue::Piece(Tet::J) => 6,
+        CellValue::Piece(Tet::S) => 7,
+        CellValue::Garbage => 8,
+    }
+}
+
+fn code_tet(code: u32) -> Option<Tet> {
+    match code {
+        1 => Some(Tet::I),
+        2 => Some(Tet::L),
+        3 => Some(Tet::O),
+        4 => Some(Tet::Z),
+        5 => Some(Tet::T),
+        6 => Some(Tet::J),
+        7 => Some(Tet::S),
+        _ => None,
+    }
+}
+
+fn code_cell(code: u32) -> CellValue {
+    match (code, code_tet(code)) {
+        (_, Some(tet)) => CellValue::Piece(tet),
+        (8, None) => CellValue::Garbage,
+        _ => CellValue::Empty,
+    }
+}
+
+fn rot_code(rs: RotState) -> u32 {
+    match rs {
+        RotState::R2 => 0,
+        RotState::R1 => 1,
+        RotState::R0 => 2,
+        RotState::R3 => 3,
+    }
+}
+
+fn code_rot(code: u32) -> RotState {
+    match code {
+        0 => RotState::R2,
+        1 => RotState::R1,
+        2 => RotState::R0,
+        _ => RotState::R3,
+    }
+}
+
+impl FumenPage {
+    /// Field codes in fumen order: top row first, garbage row last.
+    fn codes(&self) -> [u32; FIELD_BLOCKS] {
+        let mut codes = [0; FIELD_BLOCKS];
+        for (i, code) in codes.iter_mut().enumerate() {
+            let (row, x) = (i / FUMEN_WIDTH, i % FUMEN_WIDTH);
+            let cell = if row == FUMEN_HEIGHT {
+                self.garbage_row[x]
+            } else {
+                self.field[FUMEN_HEIGHT - 1 - row][x]
+            };
+            *code = cell_code(cell);
+        }
+        codes
+    }
+
+    fn set_codes(&mut self, codes: &[u32; FIELD_BLOCKS]) {
+        for (i, code) in codes.iter().enumerate() {
+            let (row, x) = (i / FUMEN_WIDTH, i % FUMEN_WIDTH);
+            let cell = code_cell(*code);
+            if row == FUMEN_HEIGHT {
+                self.garbage_row[x] = cell;
+            } else {
+                self.field[FUMEN_HEIGHT - 1 - row][x] = cell;
+            }
+        }
+    }
+
+    /// The field the next page starts from.
+    fn next_field(&self) -> Self {
+        let mut next = Self {
+            field: self.field,
+            garbage_row: self.garbage_row,
+            ..Default::default()
+        };
+        if !self.lock {
+            return next;
+        }
+        if let Some(piece) = self.piece {
+            for (y, x) in piece.cells() {
+                if (0..FUMEN_HEIGHT as i8).contains(&y)
+                    && (0..FUMEN_WIDTH as i8).contains(&x)
+                {
+                    next.field[y as usize][x as usize] = CellValue::Piece(piece.tet);
+                }
+            }
+        }
+        let mut rows: Vec<_> = next
+            .field
+            .iter()
+            .filter(|row| row.contains(&CellValue::Empty))
+            .copied()
+            .collect();
+        rows.resize(FUMEN_HEIGHT, [CellValue::Empty; FUMEN_WIDTH]);
+        next.field.copy_from_slice(&rows);
+        if self.rise {
+            next.field.copy_within(0..FUMEN_HEIGHT - 1, 1);
+            next.field[0] = next.garbage_row;
+            next.garbage_row = [CellValue::Empty; FUMEN_WIDTH];
+        }
+        if self.mirror {
+            for row in next.field.iter_mut() {
+                row.reverse();
+            }
+        }
+        next
+    }
+
+    pub fn quiz(&self) -> Option<FumenQuiz> {
+        FumenQuiz::parse(&self.comment)
+    }
+
+    /// The field as a board; the garbage row is left out.
+    pub fn board(&self) -> BoardMatrix {
+        let mut board = BoardMatrix::empty();
+        for (y, row) in self.field.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                board.set_cell(y as i8, x as i8, *cell);
+            }
+        }
+        board
+    }
+
+    /// Page with the cells of `board` that fit in a fumen field; ghost
+    /// cells are left out.
+    pub fn from_board(board: &BoardMatrix) -> Self {
+        let mut page = Self::default();
+        for (y, row) in page.field.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = match board.get_cell(y as i8, x as i8) {
+                    Some(CellValue::Ghost) | None => CellValue::Empty,
+                    Some(c) => c,
+                };
+            }
+        }
+        page
+    }
+
+    /// One frame of a game: the locked cells, the falling piece, and the
+    /// hold and next pieces as a quiz comment.
+    pub fn from_state(state: &GameState) -> Self {
+        let mut board = state.main_board;
+        if let Some(pcs) = state.current_pcs {
+            let _ = board.delete_piece(&pcs);
+        }
+        let mut page = Self::from_board(&board);
+        page.piece = state.current_pcs.and_then(|pcs| {
+            let shape = pcs.tet.shape(pcs.rot_system, pcs.rs);
+            let mut cells = vec![];
+            for (y, row) in shape.iter().enumerate() {
+                for (x, filled) in row.iter().enumerate() {
+                    if *filled {
+                        cells.push((pcs.pos.0 + y as i8, pcs.pos.1 + x as i8));
+                    }
+                }
+            }
+            cells.sort();
+            find_piece(pcs.tet, pcs.rs, &cells)
+        });
+        page.comment = FumenQuiz {
+            hold: state.hold_pcs.map(|h| h.tet),
+            current: state.current_pcs.map(|p| p.tet),
+            next: state.get_next_pcs(),
+        }
+        .to_comment();
+        page
+    }
+}
+
+/// Fumen piece covering `cells`, trying the same rotation first: rotation
+/// systems do not agree on where the pieces sit in their box.
+fn find_piece(tet: Tet, rs: RotState, cells: &[(i8, i8)]) -> Option<FumenPiece> {
+    let (y0, x0) = *cells.first()?;
+    let mut rotations = vec![rs];
+    rotations.extend(
+        [RotState::R0, RotState::R1, RotState::R2, RotState::R3]
+            .into_iter()
+            .filter(|r| *r != rs),
+    );
+    for rs in rotations {
+        for dy in -3..=3 {
+            for dx in -3..=3 {
+                let piece = FumenPiece {
+                    tet,
+                    rs,
+                    x: x0 + dx,
+                    y: y0 + dy,
+                };
+                let mut found = piece.cells().to_vec();
+                found.sort();
+                if found == cells {
+                    return Some(piece);
+                }
+            }
+        }
+    }
+    None
+}
+
+struct FumenReader {
+    values: Vec<u32>,
+    pos: usize,
+}
+
+impl FumenReader {
+    fn poll(&mut self, n: usize) -> anyhow::Result<u32> {
+        let chunk = self
+            .values
+            .get(self.pos..self.pos + n)
+            .context("fumen data ends too early")?;
+        self.pos += n;
+        Ok(chunk.iter().rev().fold(0, |acc, v| acc * 64 + v))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.values.len()
+    }
+}
+
+fn push_value(out: &mut Vec<u32>, mut value: u32, n: usize) {
+    for _ in 0..n {
+        out.push(value % 64);
+        value /= 64;
+    }
+}
+
+/// The `escape()` of JavaScript, used by fumen for comments.
+fn js_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) {
+            out.push(c);
+        } else if (c as u32) < 256 {
+            out.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out.push_str(&format!("%u{:04X}", unit));
+            }
+        }
+    }
+    out
+}
+
+fn js_unescape(s: &str) -> String {
+    let mut units = vec![];
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let hex = |s: &str| {
+            s.chars()
+                .all(|c| c.is_ascii_hexdigit())
+                .then(|| u16::from_str_radix(s, 16).ok())
+                .flatten()
+        };
+        let escaped = match (c, rest.get(1..2)) {
+            ('%', Some("u")) => rest.get(2..6).and_then(hex).map(|u| (u, 6)),
+            ('%', _) => rest.get(1..3).and_then(hex).map(|u| (u, 3)),
+            _ => None,
+        };
+        let len = match escaped {
+            Some((unit, len)) => {
+                units.push(unit);
+                len
+            }
+            None => {
+                units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+                c.len_utf8()
+            }
+        };
+        rest = &rest[len..];
+    }
+    String::from_utf16_lossy(&units)
+}
+
+/// Decode a fumen v115 string; anything before `115@`, like the site URL,
+/// is ignored.
+pub fn decode_fumen(data: &str) -> anyhow::Result<Vec<FumenPage>> {
+    let (_, data) = data.split_once("115@").context("not a fumen v115 string")?;
+    let values = data
+        .chars()
+        .filter(|c| *c != '?' && !c.is_whitespace())
+        .map(|c| {
+            TABLE
+                .iter()
+                .position(|t| *t as char == c)
+                .map(|i| i as u32)
+                .with_context(|| format!("bad fumen character {c:?}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut reader = FumenReader { values, pos: 0 };
+
+    let mut pages: Vec<FumenPage> = vec![];
+    let mut field = FumenPage::default().codes();
+    let mut repeat = 0;
+    let mut comment = String::new();
+    while !reader.is_empty() {
+        if let Some(prev) = pages.last() {
+            field = prev.next_field().codes();
+        }
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+            let mut changed = true;
+            while index < FIELD_BLOCKS {
+                let value = reader.poll(2)?;
+                let diff = value / FIELD_BLOCKS as u32;
+                let count = value % FIELD_BLOCKS as u32 + 1;
+                if diff == EMPTY_DIFF && count == FIELD_BLOCKS as u32 {
+                    changed = false;
+                }
+                for _ in 0..count {
+                    let cell = field.get_mut(index).context("fumen field too long")?;
+                    *cell = (*cell + diff)
+                        .checked_sub(EMPTY_DIFF)
+                        .context("bad fumen field")?;
+                    index += 1;
+                }
+            }
+            if !changed {
+                repeat = reader.poll(1)?;
+            }
+        }
+
+        let mut value = reader.poll(3)?;
+        let mut next = |n: u32| {
+            let v = value % n;
+            value /= n;
+            v
+        };
+        let tet = next(8);
+        let rs = code_rot(next(4));
+        let location = next(FIELD_BLOCKS as u32);
+        let rise = next(2) == 1;
+        let mirror = next(2) == 1;
+        let _colorize = next(2);
+        let has_comment = next(2) == 1;
+        let lock = next(2) == 0;
+
+        if has_comment {
+            let len = reader.poll(2)? as usize;
+            let mut escaped = String::new();
+            for _ in 0..len.div_ceil(4) {
+                let mut value = reader.poll(5)?;
+                for _ in 0..4 {
+                    escaped.push((b' ' + (value % COMMENT_TABLE_LEN) as u8) as char);
+                    value /= COMMENT_TABLE_LEN;
+                }
+            }
+            escaped.truncate(len);
+            comment = js_unescape(&escaped);
+        }
+
+        let mut page = FumenPage {
+            piece: code_tet(tet)
+                .map(|tet| FumenPiece::from_location(tet, rs, location)),
+            comment: comment.clone(),
+            lock,
+            rise,
+            mirror,
+            ..Default::default()
+        };
+        page.set_codes(&field);
+        pages.push(page);
+    }
+    Ok(pages)
+}
+
+/// Encode pages as a fumen v115 string, with the `v115@` prefix.
+pub fn encode_fumen(pages: &[FumenPage]) -> String {
+    let mut out = vec![];
+    let mut prev_field = FumenPage::default().codes();
+    let mut prev_comment = String::new();
+    let mut repeat_at: Option<usize> = None;
+    for (i, page) in pages.iter().enumerate() {
+        if let Some(prev) = i.checked_sub(1).map(|i| &pages[i]) {
+            prev_field = prev.next_field().codes();
+        }
+        let field = page.codes();
+        let diffs: Vec<u32> = field
+            .iter()
+            .zip(prev_field.iter())
+            .map(|(cur, prev)| cur + EMPTY_DIFF - prev)
+            .collect();
+        let changed = diffs.iter().any(|d| *d != EMPTY_DIFF);
+        match repeat_at {
+            Some(at) if !changed && out[at] < 63 => out[at] += 1,
+            _ => {
+                let mut start = 0;
+                while start < FIELD_BLOCKS {
+                    let diff = diffs[start];
+                    let count =
+                        diffs[start..].iter().take_while(|d| **d == diff).count();
+                    push_value(
+                        &mut out,
+                        diff * FIELD_BLOCKS as u32 + count as u32 - 1,
+                        2,
+                    );
+                    start += count;
+                }
+                repeat_at = None;
+                if !changed {
+                    out.push(0);
+                    repeat_at = Some(out.len() - 1);
+                }
+            }
+        }
+
+        let has_comment = if i == 0 {
+            !page.comment.is_empty()
+        } else {
+            page.comment != prev_comment
+        };
+        let (tet, rs, location) = match page.piece {
+            Some(p) => (cell_code(CellValue::Piece(p.tet)), p.rs, p.location()),
+            None => (0, RotState::R2, 0),
+        };
+        let mut value = !page.lock as u32;
+        value = value * 2 + has_comment as u32;
+        value = value * 2 + (i == 0) as u32;
+        value = value * 2 + page.mirror as u32;
+        value = value * 2 + page.rise as u32;
+        value = value * FIELD_BLOCKS as u32 + location;
+        value = value * 4 + rot_code(rs);
+        value = value * 8 + tet;
+        push_value(&mut out, value, 3);
+
+        if has_comment {
+            let mut escaped = js_escape(&page.comment);
+            escaped.truncate(MAX_COMMENT_LEN);
+            push_value(&mut out, escaped.len() as u32, 2);
+            for chunk in escaped.as_bytes().chunks(4) {
+                let value = chunk
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, c| acc * COMMENT_TABLE_LEN + (c - b' ') as u32);
+                push_value(&mut out, value, 5);
+            }
+            prev_comment = page.comment.clone();
+        }
+    }
+
+    let data: String = out.iter().map(|v| TABLE[*v as usize] as char).collect();
+    // fumen breaks long data with `?`, the first chunk is shorter
+    let mut chunks = vec![&data[..data.len().min(42)]];
+    let mut rest = &data[data.len().min(42)..];
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(rest.len().min(47));
+        chunks.push(chunk);
+        rest = tail;
+    }
+    format!("v115@{}", chunks.join("?"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn empty_field() {
+        let pages = decode_fumen("v115@vhAAgH").unwrap();
+        assert_eq!(pages, vec![FumenPage::default()]);
+        assert_eq!(encode_fumen(&pages), "v115@vhAAgH");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn single_garbage_cell() {
+        let mut page = FumenPage::default();
+        page.field[0][0] = CellValue::Garbage;
+        assert_eq!(encode_fumen(std::slice::from_ref(&page)), "v115@bhA8SeAgH");
+        let decoded = decode_fumen("http://fumen.zui.jp/?v115@bhA8SeAgH").unwrap();
+        assert_eq!(decoded, vec![page]);
+        assert_eq!(decoded[0].board().get_cell(0, 0), Some(CellValue::Garbage));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn same_placement_same_location() {
+        let piece = |tet, rs, x, y| FumenPiece { tet, rs, x, y };
+        for (a, b) in [
+            (
+                piece(Tet::I, RotState::R0, 4, 0),
+                piece(Tet::I, RotState::R2, 5, 0),
+            ),
+            (
+                piece(Tet::S, RotState::R0, 4, 1),
+                piece(Tet::S, RotState::R2, 4, 2),
+            ),
+            (
+                piece(Tet::Z, RotState::R1, 4, 1),
+                piece(Tet::Z, RotState::R3, 5, 1),
+            ),
+        ] {
+            let mut cells_a = a.cells();
+            let mut cells_b = b.cells();
+            cells_a.sort();
+            cells_b.sort();
+            assert_eq!(cells_a, cells_b);
+            assert_eq!(a.location(), b.location());
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn pages_round_trip() {
+        let mut first = FumenPage::default();
+        for x in 0..9 {
+            first.field[0][x] = CellValue::Garbage;
+        }
+        first.piece = Some(FumenPiece {
+            tet: Tet::I,
+            rs: RotState::R1,
+            x: 9,
+            y: 1,
+        });
+        first.comment = "#Q=[T](I)SZO 100% ok, \u{1F600}".to_string();
+        // the I clears the bottom row
+        let mut second = FumenPage::default();
+        for y in 0..3 {
+            second.field[y][9] = CellValue::Piece(Tet::I);
+        }
+        second.comment = first.comment.clone();
+        let third = FumenPage {
+            field: second.field,
+            ..Default::default()
+        };
+        let pages = vec![first, second, third.clone(), third];
+        let data = encode_fumen(&pages);
+        assert_eq!(decode_fumen(&data).unwrap(), pages);
+
+        let quiz = pages[0].quiz().unwrap();
+        assert_eq!(quiz.hold, Some(Tet::T));
+        assert_eq!(quiz.current, Some(Tet::I));
+        assert_eq!(quiz.next, vec![Tet::S, Tet::Z, Tet::O]);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn game_state_round_trip() {
+        let mut state = GameState::new(&[3; 32], 0);
+        for x in 0..9 {
+            state.main_board.set_cell(0, x, CellValue::Garbage);
+        }
+        let page = FumenPage::from_state(&state);
+        let pages = decode_fumen(&encode_fumen(std::slice::from_ref(&page))).unwrap();
+        assert_eq!(pages, vec![page.clone()]);
+
+        let loaded =
+            GameState::from_fumen_page(&pages[0], &[5; 32], 0, Default::default());
+        assert_eq!(loaded.main_board.get_cell(0, 0), Some(CellValue::Garbage));
+        assert_eq!(
+            loaded.current_pcs.unwrap().tet,
+            state.current_pcs.unwrap().tet
+        );
+        assert_eq!(loaded.get_next_pcs(), state.get_next_pcs());
+        assert_eq!(FumenPage::from_state(&loaded).comment, page.comment);
+    }
+}
@@ -1,19 +1,35 @@
 #![allow(clippy::manual_memcpy)]
 
-use super::random::{accept_event, shuffle_tets, GameSeed};
+use super::random::{accept_event, GameSeed};
 use crate::{tet::get_random_seed, timestamp::get_timestamp_now_ms};
 
 use super::{
+    events::{GameEvent, GameEventList},
+    finesse::FinesseTracker,
+    fumen::FumenPage,
+    garbage::{next_garbage_hole, GarbageQueue},
+    legacy,
+    lock_delay::LockDelayState,
     matrix::{BoardMatrix, BoardMatrixHold, BoardMatrixNext, CellValue},
     rot::{RotDirection, RotState},
+    rot_system::RotationSystemKind,
+    rules::{GameRules, RulesVersion},
+    scoring::LineClear,
+    setup::GameSetup,
+    spin::{detect_spin, SpinKind},
+    stats::GameStats,
     tetpcs::{Tet, TetAction},
 };
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
+/// Enough room for a 14-bag on top of the 7 or more pieces still queued.
+const NEXT_PCS_BUFFER_LEN: usize = 28;
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameState {
-    pub score: i32,              // 24 bits
+    pub score: i32, // 24 bits
+    pub level: u16,
     pub is_t_spin: bool,         // 1 bit
     pub is_t_mini_spin: bool,    // 1 bit
     pub is_b2b: bool,            // 1 bit
@@ -32,31 +48,53 @@ pub struct GameState {
     pub seed: GameSeed,      // 32 bytes = 256bit
     pub init_seed: GameSeed, // 256bit
     pub start_time: i64,     // n--ai acsf
+    /// time of the last accepted action
+    pub last_event_time: i64,
     pub total_lines: u16,
     pub total_garbage_sent: u16, // 15 bit
     pub garbage_recv: u16,       // 15 bit
     pub garbage_applied: u16,
+    /// received garbage cancelled by our own attack
+    pub garbage_cancelled: u16,
+    pub garbage_queue: GarbageQueue,
+    /// hole column of the last garbage line that entered the board
+    pub garbage_hole: Option<i8>,
     pub total_moves: u16, // 16 bit
 
     pub last_segment: GameReplaySegment, // OK
     pub last_segment_idx: u16,
 
     // pub next_pcs: VecDeque<Tet>,             // 42 bit
-    pub next_pcs_bags: [Tet; 14],
+    pub next_pcs_bags: [Tet; NEXT_PCS_BUFFER_LEN],
     pub next_pcs_idx: u8,
+    pub next_pcs_len: u8,
+
+    pub rules: GameRules,
+    pub lock_delay: LockDelayState,
+    pub stats: GameStats,
+    /// keys of the current piece, judged into `stats` when it locks
+    pub finesse: FinesseTracker,
+    #[serde(skip)]
+    pub events: GameEventList,
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GameReplayInit {
     pub init_seed: GameSeed,
     pub start_time: i64,
+    pub rules: GameRules,
+    /// starting position of setup games; packed, since every state holds
+    /// a copy of its last segment
+    pub setup: Option<GameSetup>,
 }
 
 impl GameReplayInit {
-    pub fn empty(seed: &GameSeed, start_time: i64) -> Self {
+    pub fn empty(seed: &GameSeed, start_time: i64, rules: GameRules) -> Self {
         Self {
             init_seed: *seed,
             start_time,
+            rules,
+            setup: None,
         }
     }
 }
@@ -67,6 +105,8 @@ pub enum GameOverReason {
     Disconnect,
     Abandon,
     Win,
+    /// the goal of a solo mode was reached
+    Complete,
 }
 
 impl GameOverReason {
@@ -76,6 +116,7 @@ impl GameOverReason {
             Self::Disconnect => 0,
             Self::Abandon => 0,
             Self::Win => 100,
+            Self::Complete => 100,
         }
     }
 }
@@ -125,22 +166,101 @@ pub struct CurrentPcsInfo {
     pub tet: Tet,      // 3bit
     pub rs: RotState,  // 2bit
     pub id: u16,       // 14bit
+    pub rot_system: RotationSystemKind,
 }
 
 impl GameState {
-    fn add_pending_received_garbage(&mut self) {
-        while self.garbage_applied < self.garbage_recv {
-            self.main_board.inject_single_garbage_line(self.seed);
-            self.garbage_applied += 1;
+    /// Games from before rules versions, see `GameRules::legacy`.
+    fn is_legacy(&self) -> bool {
+        self.rules.version == RulesVersion::V0
+    }
+    fn add_pending_received_garbage(&mut self, event_time: i64) {
+        let rules = self.rules.garbage;
+        let applied_before = self.garbage_applied;
+        if self.is_legacy() {
+            // everything received so far, all lines with the same hole
+            while self.garbage_applied < self.garbage_recv {
+                self.main_board.inject_single_garbage_line(self.seed);
+                self.garbage_applied += 1;
+            }
+        }
+        while let Some(lines) = self
+            .garbage_queue
+            .pop_ready(rules.entry_delay_ms, event_time)
+        {
+            for i in 0..lines {
+                let messiness = if i == 0 {
+                    rules.attack_messiness
+                } else {
+                    rules.line_messiness
+                };
+                let hole = next_garbage_hole(
+                    &self.seed,
+                    self.garbage_applied,
+                    self.garbage_hole,
+                    messiness,
+                    self.main_board.get_num_cols() as i8,
+                );
+                self.garbage_hole = Some(hole);
+                self.main_board.inject_garbage_line(hole);
+                self.garbage_applied += 1;
+            }
+        }
+        if self.garbage_applied > applied_before {
+            self.events.push(GameEvent::GarbageApplied {
+                lines: self.garbage_applied - applied_before,
+            });
         }
     }
+    /// Total garbage sent by opponents so far. The events of the last
+    /// action are kept, garbage can arrive before anyone has read them.
     pub fn apply_raw_received_garbage(&mut self, new_garbage: u16) {
         if new_garbage > self.garbage_recv {
+            self.events.push(GameEvent::GarbageReceived {
+                lines: new_garbage - self.garbage_recv,
+            });
             self.garbage_recv = new_garbage;
         }
     }
+    /// Queue garbage that arrived since the last action. The arrival time
+    /// is the time of the action, so replays queue it the same way.
+    fn queue_received_garbage(&mut self, event_time: i64) {
+        if self.is_legacy() {
+            return;
+        }
+        let known =
+            self.garbage_applied + self.garbage_cancelled + self.garbage_queue.total();
+        if self.garbage_recv > known {
+            self.garbage_queue
+                .push(self.garbage_recv - known, event_time);
+        }
+    }
+    /// Dig modes start with garbage already on the board. Holes come from
+    /// the init seed, so everyone playing the same seed digs the same rows.
+    fn add_initial_garbage(&mut self) {
+        let rules = self.rules.garbage;
+        for i in 0..rules.initial_rows as u16 {
+            let hole = next_garbage_hole(
+                &self.init_seed,
+                i,
+                self.garbage_hole,
+                rules.line_messiness,
+                self.main_board.get_num_cols() as i8,
+            );
+            self.garbage_hole = Some(hole);
+            self.main_board.inject_garbage_line(hole);
+        }
+    }
+    /// Received garbage that did not enter the board and was not cancelled.
+    pub fn pending_garbage(&self) -> u16 {
+        self.garbage_recv - self.garbage_applied - self.garbage_cancelled
+    }
     pub fn current_time_string(&self) -> String {
-        let dt_s = get_timestamp_now_ms() - self.start_time;
+        self.time_string_at(get_timestamp_now_ms())
+    }
+    /// Time since the start of the game at `now`, read from a `Clock`.
+    pub fn time_string_at(&self, now: i64) -> String {
+        let dt_s = now - self.start_time;
         if dt_s < 0 {
             return "future".to_string();
         }
@@ -152,16 +272,49 @@ impl GameState {
         self.game_over_reason.is_some()
     }
 
+    /// Milliseconds from the start of the game to the last action.
+    pub fn play_time_ms(&self) -> i64 {
+        self.last_event_time - self.start_time
+    }
+
+    /// End the game from outside the game rules, e.g. from a `RuleManager`.
+    pub fn finish(&mut self, reason: GameOverReason) {
+        self.events.clear();
+        self.events.push(GameEvent::GameOver(reason));
+        self.game_over_reason = Some(reason);
+        self.last_segment = GameReplaySegment::GameOver(reason);
+    }
+
     pub fn new(seed: &GameSeed, start_time: i64) -> Self {
-        let (bag1, seed1) = shuffle_tets(seed, start_time);
-        let (bag2, seed2) = shuffle_tets(&seed1, start_time);
-        let mut next_pcs_bags = [Tet::I; 14];
-        for i in 0..7 {
-            next_pcs_bags[i] = bag1[i];
-            next_pcs_bags[i + 7] = bag2[i];
-        }
+        Self::new_with_rules(seed, start_time, GameRules::default())
+    }
+
+    pub fn new_with_rules(seed: &GameSeed, start_time: i64, rules: GameRules) -> Self {
+        Self::from_init(&GameReplayInit::empty(seed, start_time, rules))
+    }
+
+    /// Start from a preset board, queue and hold piece instead of an empty
+    /// board; the initial garbage of the rules is not added.
+    pub fn from_setup(
+        setup: &GameSetup,
+        seed: &GameSeed,
+        start_time: i64,
+        rules: GameRules,
+    ) -> Self {
+        Self::from_init(&GameReplayInit {
+            setup: Some(*setup),
+            ..GameReplayInit::empty(seed, start_time, rules)
+        })
+    }
+
+    /// The first state of a game, as recorded in its init segment.
+    pub fn from_init(init: &GameReplayInit) -> Self {
+        let seed = &init.init_seed;
+        let start_time = init.start_time;
+        let rules = init.rules;
         let mut new_state = Self {
             score: 0,
+            level: rules.level.start_level,
             combo_counter: -1,
             is_t_spin: false,
             is_t_mini_spin: false,
@@ -174,26 +327,63 @@ impl GameState {
             game_over_reason: None,
             hold_pcs: None,
             current_id: 0,
-            seed: seed2,
+            seed: *seed,
             init_seed: *seed,
-            last_segment: GameReplaySegment::Init(GameReplayInit::empty(
-                seed, start_time,
-            )),
+            last_segment: GameReplaySegment::Init(*init),
             last_segment_idx: 0,
             start_time,
+            last_event_time: start_time,
             total_lines: 0,
             total_garbage_sent: 0,
             garbage_recv: 0,
             total_moves: 0,
             garbage_applied: 0,
+            garbage_cancelled: 0,
+            garbage_queue: GarbageQueue::empty(),
+            garbage_hole: None,
             next_pcs_idx: 0,
-            next_pcs_bags,
+            next_pcs_len: 0,
+            next_pcs_bags: [Tet::I; NEXT_PCS_BUFFER_LEN],
+            rules,
+            lock_delay: LockDelayState::new(0),
+            stats: GameStats::default(),
+            finesse: FinesseTracker::for_game_stats(),
+            events: GameEventList::default(),
         };
-        let _ = new_state.put_next_piece(start_time, None);
+        let mut first_pcs = None;
+        if let Some(setup) = init.setup {
+            new_state.main_board = setup.board();
+            new_state.hold_pcs = setup.hold.map(|tet| HoldPcsInfo { can_use: true, tet });
+            // the fixed queue goes first, the randomizer carries on after it
+            let queue = setup.queue();
+            if let Some((current, next)) = queue.split_first() {
+                first_pcs = Some(*current);
+                new_state.next_pcs_bags[..next.len()].copy_from_slice(next);
+                new_state.next_pcs_len = next.len() as u8;
+            }
+        }
+        while new_state.next_pcs_len < 14 {
+            new_state.push_next_pcs_chunk(start_time);
+        }
+        if init.setup.is_none() {
+            new_state.add_initial_garbage();
+        }
+        let _ = new_state.put_next_piece(start_time, first_pcs);
+        new_state.apply_20g();
         new_state.put_ghost();
         new_state
     }
 
+    /// Start from a fumen page, see `GameSetup::from_fumen_page`.
+    pub fn from_fumen_page(
+        page: &FumenPage,
+        seed: &GameSeed,
+        start_time: i64,
+        rules: GameRules,
+    ) -> Self {
+        Self::from_setup(&GameSetup::from_fumen_page(page), seed, start_time, rules)
+    }
+
     pub fn new_random() -> Self {
         let seed = get_random_seed();
         let start_time = get_timestamp_now_ms();
@@ -258,129 +448,129 @@ impl GameState {
 
     fn clear_line(&mut self) {
         let mut lines = 0;
+        let mut garbage_cleared = 0;
         while let Some(line) = self.can_clear_line() {
+            if self.main_board.rows()[line as usize].contains(&CellValue::Garbage) {
+                garbage_cleared += 1;
+            }
             self.main_board.clear_line(line);
 
             lines += 1;
         }
-        self.add_score_for_clear_line(lines);
-        self.add_garbage_sent_for_clear_line(lines);
+        let clear = self.add_score_for_clear_line(lines);
+        self.push_clear_events(&clear);
+        let attack = self.add_garbage_sent_for_clear_line(&clear);
+        self.stats.on_clear(&clear, attack, garbage_cleared);
         self.total_lines += lines;
+        let level = self.rules.level.level_for_lines(self.total_lines);
+        if level > self.level {
+            self.events.push(GameEvent::LevelUp { level });
+        }
+        self.level = level;
+        self.is_t_spin = false;
+        self.is_t_mini_spin = false;
     }
 
-    fn add_garbage_sent_for_clear_line(&mut self, lines: u16) {
-        self.total_garbage_sent += match self.combo_counter {
-            1 | 2 => 1,
-            3 | 4 => 2,
-            5 | 6 => 3,
-            _i if _i >= 7 => 4,
-            _ => 0,
-        };
-
-        self.total_garbage_sent += match lines {
-            4 => 4,
-            3 => 2,
-            2 => 1,
-            _ => 0,
-        };
-
-        if self.is_gameboard_empty() {
-            self.total_garbage_sent += match lines {
-                0 => 0,
-                _ => 10,
-            };
+    fn push_clear_events(&mut self, clear: &LineClear) {
+        if clear.lines > 0 {
+            self.events
+                .push(GameEvent::LineClear { lines: clear.lines });
         }
-
-        if self.is_t_spin {
-            self.total_garbage_sent += match lines {
-                1 => 2,
-                2 => 4,
-                3 => 6,
-                _ => 0,
-            };
+        if clear.spin != SpinKind::None {
+            self.events.push(GameEvent::Spin {
+                kind: clear.spin,
+                lines: clear.lines,
+            });
         }
-        if self.is_t_mini_spin {
-            self.total_garbage_sent += match lines {
-                1 => 1,
-                2 => 3,
-                3 => 5,
-                _ => 0,
-            };
+        if clear.lines == 0 {
+            return;
         }
-    }
-
-    fn add_score_for_clear_line(&mut self, lines: u16) {
-        let mut score = 0;
-        let mut score2 = 0;
-        let mut score3 = 0;
-        score += match lines {
-            1 => 40,
-            2 => 80,
-            3 => 160,
-            4 => 320,
-            _ => 0,
-        };
-
-        if self.is_gameboard_empty() {
-            score2 += match lines {
-                1 => 200,
-                2 => 400,
-                3 => 800,
-                4 => 1600,
-                _ => 0,
-            };
+        if clear.b2b {
+            self.events.push(GameEvent::BackToBack {
+                chain: self.stats.b2b_chain + 1,
+            });
+        }
+        if clear.combo > 0 {
+            self.events.push(GameEvent::Combo {
+                count: clear.combo as u16,
+            });
         }
+        if clear.perfect_clear {
+            self.events.push(GameEvent::PerfectClear);
+        }
+    }
 
-        if self.is_t_spin {
-            score3 += match lines {
-                1 => 1000,
-                2 => 2000,
-                3 => 3000,
-                _ => 0,
+    /// Returns the attack of the clear, before cancelling.
+    fn add_garbage_sent_for_clear_line(&mut self, clear: &LineClear) -> u16 {
+        let attack = if self.is_legacy() {
+            // spins never added to the attack
+            let clear = LineClear {
+                spin: SpinKind::None,
+                ..*clear
             };
+            self.rules.garbage.attack.attack(&clear)
+        } else {
+            self.rules.garbage.attack.attack(clear)
+        };
+        let mut sent = attack;
+        if self.rules.garbage.cancelling {
+            let cancelled = self.garbage_queue.cancel(attack);
+            self.garbage_cancelled += cancelled;
+            sent -= cancelled;
+            if cancelled > 0 {
+                self.events
+                    .push(GameEvent::GarbageCancelled { lines: cancelled });
+            }
         }
-        if self.is_t_mini_spin {
-            score3 += match lines {
-                1 => 666,
-                2 => 1666,
-                3 => 2666,
-                _ => 0,
-            };
+        self.total_garbage_sent += sent;
+        if sent > 0 {
+            self.events.push(GameEvent::AttackSent { lines: sent });
         }
-        self.is_b2b = (lines == 4) || (self.is_t_spin);
-        self.score += score + score2 + score3;
-        self.is_t_spin = false;
-        self.is_t_mini_spin = false;
+        attack
+    }
+
+    fn add_score_for_clear_line(&mut self, lines: u16) -> LineClear {
+        let spin = if self.is_t_spin {
+            SpinKind::Full
+        } else if self.is_t_mini_spin {
+            SpinKind::Mini
+        } else {
+            SpinKind::None
+        };
+        let difficult = lines == 4 || (lines > 0 && spin != SpinKind::None);
         if lines > 0 {
             self.combo_counter += 1;
         } else {
             self.combo_counter = -1;
         }
-        if self.combo_counter > 0 {
-            self.score += 50 * self.combo_counter as i32;
+        let clear = LineClear {
+            lines,
+            spin,
+            perfect_clear: lines > 0 && self.is_gameboard_empty(),
+            b2b: difficult && self.is_b2b,
+            combo: self.combo_counter,
+            level: self.level,
+        };
+        if self.is_legacy() {
+            self.score += legacy::clear_points(
+                lines,
+                self.is_t_spin,
+                self.is_t_mini_spin,
+                clear.perfect_clear,
+                clear.combo,
+            );
+            self.is_b2b = lines == 4 || self.is_t_spin;
+            return LineClear { b2b: false, ..clear };
         }
+        self.score += self.rules.scoring.get().clear_points(&clear);
+        if lines > 0 {
+            self.is_b2b = difficult;
+        }
+        clear
     }
 
     fn can_clear_line(&self) -> Option<i8> {
-        for (i, row) in self.main_board.rows().into_iter().enumerate() {
-            // let row = self.main_board.v[i];
-            let is_full = row
-                .iter()
-                .map(|cell| match cell {
-                    CellValue::Piece(_) => true,
-                    CellValue::Garbage => true,
-                    CellValue::Empty => false,
-                    CellValue::Ghost => false,
-                })
-                .reduce(|a, b| a & b);
-            if let Some(is_full) = is_full {
-                if is_full {
-                    return Some(i as i8);
-                }
-            }
-        }
-
-        None
+        self.main_board.first_full_row()
     }
     fn put_replay_event(&mut self, event: &GameReplayEvent, event_time: i64) {
         let idx = self.last_segment_idx;
@@ -394,23 +584,33 @@ impl GameState {
             new_garbage_applied: self.garbage_applied,
         };
         self.seed = new_slice.new_seed;
+        self.last_event_time = event_time;
         // tracing::info!("put  replay event {new_slice:?}");
         self.last_segment = GameReplaySegment::Update(new_slice);
         self.last_segment_idx += 1;
     }
 
+    fn push_next_pcs_chunk(&mut self, event_time: i64) {
+        let history = &self.next_pcs_bags[..self.next_pcs_len as usize];
+        let (new_pcs, new_seed) = self
+            .rules
+            .randomizer
+            .next_chunk(&self.seed, event_time, history);
+        for n in new_pcs {
+            self.next_pcs_bags[self.next_pcs_len as usize] = n;
+            self.next_pcs_len += 1;
+        }
+        self.seed = new_seed;
+    }
     fn refill_nextpcs(&mut self, event_time: i64) {
-        if self.next_pcs_idx >= 7 {
-            for i in 0..7 {
-                self.next_pcs_bags[i] = self.next_pcs_bags[i + 7];
-            }
-            self.next_pcs_idx -= 7;
+        if self.next_pcs_len - self.next_pcs_idx <= 7 {
+            let idx = self.next_pcs_idx as usize;
+            let len = self.next_pcs_len as usize;
+            self.next_pcs_bags.copy_within(idx..len, 0);
+            self.next_pcs_len -= self.next_pcs_idx;
+            self.next_pcs_idx = 0;
             // tracing::info!("next refill");
-            let (new_pcs2, new_seed) = shuffle_tets(&self.seed, event_time);
-            for (i, n) in new_pcs2.iter().enumerate() {
-                self.next_pcs_bags[i + 7] = *n;
-            }
-            self.seed = new_seed;
+            self.push_next_pcs_chunk(event_time);
         }
     }
     fn pop_next_pcs(&mut self, event_time: i64) -> Tet {
@@ -435,17 +635,22 @@ impl GameState {
         }
 
         self.clear_line();
-        self.add_pending_received_garbage();
+        self.add_pending_received_garbage(_event_time);
         let next_tet = match maybe_next_pcs {
             None => self.pop_next_pcs(_event_time),
             Some(x) => x,
         };
 
+        let pos = self
+            .main_board
+            .spawn_pos(next_tet, self.rules.rotation_system);
+        self.lock_delay = LockDelayState::new(pos.0);
         self.current_pcs = Some(CurrentPcsInfo {
-            pos: next_tet.spawn_pos(),
+            pos,
             tet: next_tet,
             id: self.current_id,
             rs: RotState::R0,
+            rot_system: self.rules.rotation_system,
         });
         self.current_id += 1;
 
@@ -457,6 +662,8 @@ impl GameState {
             tracing::info!("tet game over");
             self.game_over_reason = Some(GameOverReason::Knockout);
             self.last_segment = GameReplaySegment::GameOver(GameOverReason::Knockout);
+            self.events
+                .push(GameEvent::GameOver(GameOverReason::Knockout));
         } else if let Some(ref mut h) = self.hold_pcs {
             h.can_use = true;
         }
@@ -493,10 +700,24 @@ impl GameState {
         }
         Ok(())
     }
+    /// Applies a segment after the init segment of a replay.
+    pub fn accept_segment(
+        &mut self,
+        segment: &GameReplaySegment,
+    ) -> anyhow::Result<()> {
+        match segment {
+            GameReplaySegment::Init(_) => anyhow::bail!("got two init segments"),
+            GameReplaySegment::Update(slice) => self.accept_replay_slice(slice),
+            GameReplaySegment::GameOver(reason) => {
+                self.game_over_reason = Some(*reason);
+                Ok(())
+            }
+        }
+    }
     pub fn get_next_board(&self) -> BoardMatrixNext {
         let mut b = BoardMatrixNext::empty();
         let vnext = self.get_next_pcs();
-        b.spawn_nextpcs(&vnext);
+        b.spawn_nextpcs(&vnext, self.rules.rotation_system);
         b
     }
 
@@ -510,9 +731,11 @@ impl GameState {
 
     pub fn set_next_pcs(&mut self, new: Vec<Tet>) {
         self.next_pcs_idx = 0;
-        for i in 0..(new.len().min(14)) {
+        let new_len = new.len().min(NEXT_PCS_BUFFER_LEN);
+        for i in 0..new_len {
             self.next_pcs_bags[i] = new[i];
         }
+        self.next_pcs_len = self.next_pcs_len.max(new_len as u8);
     }
 
     pub fn get_hold_board(&self) -> BoardMatrixHold {
@@ -523,6 +746,7 @@ impl GameState {
                 pos: (if tet.eq(&Tet::I) { -1 } else { 0 }, 0),
                 rs: RotState::R0,
                 id: 0,
+                rot_system: self.rules.rotation_system,
             };
             if let Err(e) = b.spawn_piece(&info) {
                 tracing::warn!("hold board cannot spawn piece WTF: {:?}", e);
@@ -533,6 +757,10 @@ impl GameState {
 
     fn try_hold(&mut self, event_time: i64) -> anyhow::Result<()> {
         let current_pcs = self.current_pcs.context("no current pcs")?;
+        if !self.is_legacy() {
+            self.is_t_spin = false;
+            self.is_t_mini_spin = false;
+        }
 
         let old_hold = self.hold_pcs;
         if let Some(ref old_hold) = old_hold {
@@ -552,6 +780,9 @@ impl GameState {
         self.current_pcs = None;
 
         let maybe_old_hold = old_hold.as_ref().map(|old_hold| old_hold.tet);
+        self.events.push(GameEvent::Hold {
+            tet: current_pcs.tet,
+        });
         self.put_next_piece(event_time, maybe_old_hold)?;
         self.hold_pcs = Some(HoldPcsInfo {
             tet: current_pcs.tet,
@@ -562,15 +793,17 @@ impl GameState {
     }
 
     fn try_harddrop(&mut self, event_time: i64) -> anyhow::Result<()> {
-        let mut soft_drops: i16 = 0;
-        let current_pcs = self.current_pcs.context("no current pcs")?;
-        let mut r = self.try_auto_softdrop(event_time);
-        while r.is_ok() && current_pcs.id == self.current_pcs.unwrap().id {
-            r = self.try_auto_softdrop(event_time);
-            soft_drops += 1;
+        self.current_pcs.context("no current pcs")?;
+        let mut rows = 0;
+        while self.try_move_down() {
+            rows += 1;
         }
-        self.score += 10;
-        self.score -= (soft_drops * 2) as i32;
+        self.lock_current_pcs(event_time)?;
+        self.score += if self.is_legacy() {
+            legacy::HARD_DROP_POINTS
+        } else {
+            self.rules.scoring.get().hard_drop_points(rows)
+        };
         Ok(())
     }
 
@@ -583,28 +816,64 @@ impl GameState {
             anyhow::bail!("user soft drop would lock pcs");
         }
         *self = z;
+        if !self.is_legacy() {
+            self.score += self.rules.scoring.get().soft_drop_points(1);
+        }
         Ok(())
     }
 
     fn try_auto_softdrop(&mut self, event_time: i64) -> anyhow::Result<()> {
-        let current_pcs = self.current_pcs.context("no current pcs")?;
+        self.current_pcs.context("no current pcs")?;
+        if self.try_move_down() {
+            if self.is_legacy() {
+                // gravity scored like a soft drop
+                self.score += legacy::SOFT_DROP_POINTS;
+            }
+            return Ok(());
+        }
+        if !self.is_legacy()
+            && !self
+                .lock_delay
+                .is_expired(&self.rules.lock_delay, event_time)
+        {
+            anyhow::bail!("piece is on the ground, but lock delay not expired");
+        }
+        self.lock_current_pcs(event_time)
+    }
 
+    fn try_move_down(&mut self) -> bool {
+        let Some(current_pcs) = self.current_pcs else {
+            return false;
+        };
         if let Err(e) = self.main_board.delete_piece(&current_pcs) {
             tracing::warn!("ccannot delete picei from main board plz: {:?}", e)
         }
         let mut new_current_pcs = current_pcs;
         new_current_pcs.pos.0 -= 1;
         if self.main_board.spawn_piece(&new_current_pcs).is_ok() {
-            self.score += 2;
             self.current_pcs = Some(new_current_pcs);
             self.is_t_spin = false;
             self.is_t_mini_spin = false;
+            true
         } else {
             self.main_board.spawn_piece(&current_pcs).unwrap();
-            self.current_pcs = None;
-            self.put_next_piece(event_time, None)?;
+            false
         }
-        Ok(())
+    }
+
+    fn is_grounded(&self) -> bool {
+        let mut z = *self;
+        !z.try_move_down()
+    }
+
+    fn lock_current_pcs(&mut self, event_time: i64) -> anyhow::Result<()> {
+        let current_pcs = self.current_pcs.context("no current pcs")?;
+        self.current_pcs = None;
+        self.stats.on_lock();
+        self.events.push(GameEvent::PieceLocked {
+            tet: current_pcs.tet,
+        });
+        self.put_next_piece(event_time, None)
     }
 
     fn try_moveleft(&mut self) -> anyhow::Result<()> {
@@ -619,9 +888,27 @@ impl GameState {
 
         self.main_board.spawn_piece(&new_current_pcs)?;
         self.current_pcs = Some(new_current_pcs);
+        if !self.is_legacy() {
+            self.is_t_spin = false;
+            self.is_t_mini_spin = false;
+        }
         Ok(())
     }
 
+    /// Applies `step` until it fails, failing only if no step worked.
+    fn try_repeat(
+        &mut self,
+        mut step: impl FnMut(&mut Self) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let mut next = *self;
+        step(&mut next)?;
+        loop {
+            *self = next;
+            if step(&mut next).is_err() {
+                return Ok(());
+            }
+        }
+    }
     fn try_moveright(&mut self) -> anyhow::Result<()> {
         let current_pcs = self.current_pcs.context("no current pcs")?;
 
@@ -634,6 +921,10 @@ impl GameState {
 
         self.main_board.spawn_piece(&new_current_pcs)?;
         self.current_pcs = Some(new_current_pcs);
+        if !self.is_legacy() {
+            self.is_t_spin = false;
+            self.is_t_mini_spin = false;
+        }
         Ok(())
     }
 
@@ -646,7 +937,8 @@ impl GameState {
         let before = &current_pcs.rs;
         let after = &current_pcs.rs.rotate(rot);
 
-        for (x, y) in super::rot::srs_offsets(*before, *after, current_pcs.tet).iter() {
+        let rot_system = self.rules.rotation_system.get();
+        for (x, y) in rot_system.kicks(*before, *after, current_pcs.tet).iter() {
             let mut new_current_pcs: CurrentPcsInfo = current_pcs;
             new_current_pcs.rs = *after;
             // warning! table above in (x, y) but our repr in (y, x)
@@ -654,29 +946,20 @@ impl GameState {
             new_current_pcs.pos.1 += x;
 
             if self.main_board.spawn_piece(&new_current_pcs).is_ok() {
-                let (t_is_blocked3, t_is_blocked2) = {
-                    let (yt, xt) = new_current_pcs.pos;
-                    let mut block_counter = 0;
-                    for (dx, dy) in [(0, 0), (0, 2), (2, 0), (2, 2)] {
-                        let px = dx + xt;
-                        let py = dy + yt;
-                        block_counter += match self.main_board.get_cell(py, px) {
-                            Some(CellValue::Piece(_)) => 1,
-                            Some(CellValue::Garbage) => 1,
-                            Some(CellValue::Empty) => 0,
-                            Some(CellValue::Ghost) => 0,
-                            None => 0,
-                        };
-                    }
-                    (block_counter >= 3, block_counter >= 2)
-                };
                 self.current_pcs = Some(new_current_pcs);
-                self.is_t_spin = if new_current_pcs.tet == Tet::T {
-                    t_is_blocked3
-                } else {
-                    (*x != 0) || (*y != 0)
-                };
-                self.is_t_mini_spin = new_current_pcs.tet == Tet::T && t_is_blocked2;
+                if self.is_legacy() {
+                    (self.is_t_spin, self.is_t_mini_spin) =
+                        legacy::detect_spin(&self.main_board, &new_current_pcs, (*x, *y));
+                    return Ok(());
+                }
+                let spin = detect_spin(
+                    &self.main_board,
+                    &new_current_pcs,
+                    (*x, *y),
+                    self.rules.all_spin,
+                );
+                self.is_t_spin = spin == SpinKind::Full;
+                self.is_t_mini_spin = spin == SpinKind::Mini;
                 return Ok(());
             }
         }
@@ -695,34 +978,58 @@ impl GameState {
         }
         let mut new = *self;
         new.last_action = action;
+        new.events.clear();
+        new.queue_received_garbage(event_time);
 
-        match action {
-            TetAction::HardDrop => {
-                new.try_harddrop(event_time)?;
-            }
-            TetAction::UserSoftDrop => {
-                new.try_user_softdrop(event_time)?;
-            }
-            TetAction::AutoSoftDrop => {
-                new.try_auto_softdrop(event_time)?;
-            }
-            TetAction::MoveLeft => {
-                new.try_moveleft()?;
-            }
-            TetAction::MoveRight => {
-                new.try_moveright()?;
-            }
-            TetAction::Hold => {
-                new.try_hold(event_time)?;
-            }
-            TetAction::RotateLeft => {
-                new.try_rotate(RotDirection::Left)?;
-            }
-            TetAction::RotateRight => {
-                new.try_rotate(RotDirection::Right)?;
+        // a piece whose lock delay ran out is locked before anything else
+        if action != TetAction::AutoSoftDrop && new.lock_delay_expired(event_time) {
+            new.lock_current_pcs(event_time)?;
+        }
+        let pcs_before = new.current_pcs;
+
+        if !new.game_over() {
+            match action {
+                TetAction::HardDrop => {
+                    new.try_harddrop(event_time)?;
+                }
+                TetAction::UserSoftDrop => {
+                    new.try_user_softdrop(event_time)?;
+                }
+                TetAction::AutoSoftDrop => {
+                    new.try_auto_softdrop(event_time)?;
+                }
+                TetAction::MoveLeft => {
+                    new.try_moveleft()?;
+                }
+                TetAction::MoveRight => {
+                    new.try_moveright()?;
+                }
+                TetAction::Hold => {
+                    new.try_hold(event_time)?;
+                }
+                TetAction::RotateLeft => {
+                    new.try_rotate(RotDirection::Left)?;
+                }
+                TetAction::RotateRight => {
+                    new.try_rotate(RotDirection::Right)?;
+                }
+                TetAction::Rotate180 => {
+                    new.try_rotate(RotDirection::Half)?;
+                }
+                TetAction::Nothing => {}
+                TetAction::MoveLeftToWall => {
+                    new.try_repeat(Self::try_moveleft)?;
+                }
+                TetAction::MoveRightToWall => {
+                    new.try_repeat(Self::try_moveright)?;
+                }
+                TetAction::SoftDropToFloor => {
+                    new.try_repeat(|s| s.try_user_softdrop(event_time))?;
+                }
             }
-            TetAction::Nothing => {}
         }
+        new.apply_20g();
+        new.update_lock_delay(pcs_before, event_time);
         let ev = GameReplayEvent {
             action,
             // game_over: self.game_over,
@@ -733,9 +1040,69 @@ impl GameState {
             new.put_ghost();
         }
         new.total_moves += 1;
+        new.stats.on_action(action, new.play_time_ms());
+        let mut finesse = new.finesse;
+        finesse.on_new_state(self, &new);
+        new.finesse = finesse;
+        new.stats.on_finesse(&finesse);
         Ok(new)
     }
 
+    /// `try_action`, also returning the events of the action; they are
+    /// kept in `GameState::events` until the next transition.
+    pub fn try_action_with_events(
+        &self,
+        action: TetAction,
+        event_time: i64,
+    ) -> anyhow::Result<(Self, Vec<GameEvent>)> {
+        let new = self.try_action(action, event_time)?;
+        Ok((new, new.events.to_vec()))
+    }
+
+    /// Time between gravity steps at the current level, `None` when the
+    /// rules leave it to the player's settings.
+    pub fn gravity_interval(&self) -> Option<std::time::Duration> {
+        self.rules.level.gravity.interval(self.level)
+    }
+
+    fn apply_20g(&mut self) {
+        if !self.game_over() && self.rules.level.gravity.is_20g(self.level) {
+            while self.try_move_down() {}
+        }
+    }
+
+    fn lock_delay_expired(&self, event_time: i64) -> bool {
+        // legacy pieces only lock on a gravity tick
+        !self.is_legacy()
+            && self.current_pcs.is_some()
+            && self
+                .lock_delay
+                .is_expired(&self.rules.lock_delay, event_time)
+            && self.is_grounded()
+    }
+
+    fn update_lock_delay(
+        &mut self,
+        pcs_before: Option<CurrentPcsInfo>,
+        event_time: i64,
+    ) {
+        let (Some(before), Some(after)) = (pcs_before, self.current_pcs) else {
+            return;
+        };
+        if before.id != after.id || self.game_over() {
+            return;
+        }
+        let moved = before.pos.1 != after.pos.1 || before.rs != after.rs;
+        let grounded = self.is_grounded();
+        self.lock_delay.update(
+            &self.rules.lock_delay,
+            after.pos.0,
+            moved,
+            grounded,
+            event_time,
+        );
+    }
+
     fn put_ghost(&mut self) {
         let mut ghost_board = self.main_board;
         let info = self.current_pcs.unwrap();
@@ -743,37 +1110,13 @@ impl GameState {
             .delete_piece(&info)
             .expect("cannot delete pice in put_ghost");
 
-        let mut final_ghost_board = None;
-
-        for y in (-3..info.pos.0).rev() {
-            let mut ghost_info = info;
-            ghost_info.pos.0 = y;
-            if ghost_board.spawn_piece(&ghost_info).is_err() {
-                ghost_info.pos.0 += 1;
-                final_ghost_board = Some(ghost_info);
-                break;
-            } else if ghost_board.delete_piece(&ghost_info).is_err() {
-                tracing::warn!("cannot delete temporary ghost");
-            }
-        }
-
-        if let Some(ghost_info) = final_ghost_board {
-            let _ = self.main_board.spawn_ghost(&ghost_info);
-        }
+        let mut ghost_info = info;
+        ghost_info.pos.0 -= ghost_board.drop_distance(&info);
+        let _ = self.main_board.spawn_ghost(&ghost_info);
     }
 
     fn is_gameboard_empty(&mut self) -> bool {
-        let mut gameboard_empty = true;
-        for y in 0..self.main_board.get_num_rows() {
-            for x in 0..self.main_board.get_num_cols() {
-                let value = self.main_board.get_cell(y as i8, x as i8).unwrap();
-                // let value = (&self.main_board.v)[y][x];
-                if !(value.eq(&CellValue::Ghost) || value.eq(&CellValue::Empty)) {
-                    gameboard_empty = false;
-                }
-            }
-        }
-        gameboard_empty
+        self.main_board.is_empty()
     }
 
     fn clear_ghost(&mut self) {
@@ -803,11 +1146,12 @@ impl GameState {
     }
 }
 
+/// The init segment holds the rules and their `RulesVersion`, so every
+/// replay is simulated with the engine version it was recorded with;
+/// `GameState` checks `RulesVersion::V0` where the legacy engine differs.
 pub fn segments_to_states(all_segments: &[GameReplaySegment]) -> Vec<GameState> {
     let mut current_state = match all_segments.first() {
-        Some(GameReplaySegment::Init(_replay)) => {
-            GameState::new(&_replay.init_seed, _replay.start_time)
-        }
+        Some(GameReplaySegment::Init(_replay)) => GameState::from_init(_replay),
         _ => {
             tracing::info!("got no init segment");
             return vec![];
@@ -816,20 +1160,9 @@ pub fn segments_to_states(all_segments: &[GameReplaySegment]) -> Vec<GameState>
     let mut all_states = vec![];
     all_states.push(current_state);
     for segment in &all_segments[1..] {
-        match segment {
-            GameReplaySegment::Init(_) => {
-                tracing::error!("got two init segments");
-                return vec![];
-            }
-            GameReplaySegment::Update(_slice) => {
-                if let Err(e) = current_state.accept_replay_slice(_slice) {
-                    tracing::error!("failed to accept replay slice: {:#?}", e);
-                    return vec![];
-                }
-            }
-            GameReplaySegment::GameOver(reason) => {
-                current_state.game_over_reason = Some(*reason);
-            }
+        if let Err(e) = current_state.accept_segment(segment) {
+            tracing::error!("failed to accept replay segment: {:#?}", e);
+            return vec![];
         }
         all_states.push(current_state);
     }
@@ -0,0 +1,414 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    random::{get_rng, GameSeed},
+    scoring::LineClear,
+    spin::SpinKind,
+};
+
+/// Lines of garbage sent for a clear. The default is the table this game
+/// always used.
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct AttackTable {
+    /// indexed by lines cleared, without a spin
+    pub lines: [u8; 5],
+    /// indexed by lines cleared
+    pub t_spin: [u8; 4],
+    /// indexed by lines cleared
+    pub t_spin_mini: [u8; 4],
+    /// indexed by combo counter, the last entry is used for longer combos
+    pub combo: [u8; 8],
+    pub perfect_clear: u8,
+    /// extra lines for a clear that continues a back-to-back chain
+    pub b2b_bonus: u8,
+}
+
+impl Default for AttackTable {
+    fn default() -> Self {
+        Self {
+            lines: [0, 0, 1, 2, 4],
+            t_spin: [0, 2, 4, 6],
+            t_spin_mini: [0, 1, 3, 5],
+            combo: [0, 1, 1, 2, 2, 3, 3, 4],
+            perfect_clear: 10,
+            b2b_bonus: 0,
+        }
+    }
+}
+
+impl AttackTable {
+    /// Guideline multiplayer table, with mini spins worth less and a
+    /// back-to-back bonus.
+    pub fn guideline() -> Self {
+        Self {
+            lines: [0, 0, 1, 2, 4],
+            t_spin: [0, 2, 4, 6],
+            t_spin_mini: [0, 0, 1, 2],
+            combo: [0, 1, 1, 2, 2, 3, 3, 4],
+            perfect_clear: 10,
+            b2b_bonus: 1,
+        }
+    }
+
+    pub fn attack(&self, clear: &LineClear) -> u16 {
+        if clear.lines == 0 {
+            return 0;
+        }
+        let lines = clear.lines as usize;
+        let base = match clear.spin {
+            SpinKind::None => self.lines[lines.min(4)],
+            SpinKind::Mini => self.t_spin_mini[lines.min(3)],
+            SpinKind::Full => self.t_spin[lines.min(3)],
+        };
+        let combo = self.combo[(clear.combo.max(0) as usize).min(7)];
+        let mut attack = base as u16 + combo as u16;
+        if clear.b2b {
+            attack += self.b2b_bonus as u16;
+        }
+        if clear.perfect_clear {
+            attack += self.perfect_clear as u16;
+        }
+        attack
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct GarbageRules {
+    pub attack: AttackTable,
+    /// outgoing attack first cancels garbage still waiting in the queue
+    pub cancelling: bool,
+    /// received garbage waits this long before it can enter the board
+    pub entry_delay_ms: u16,
+    /// chance in percent that the hole moves between two lines of the same
+    /// attack
+    pub line_messiness: u8,
+    /// chance in percent that the hole moves between two attacks
+    pub attack_messiness: u8,
+    /// garbage rows on the board when the game starts, holes picked with
+    /// `line_messiness`
+    pub initial_rows: u8,
+}
+
+impl Default for GarbageRules {
+    fn default() -> Self {
+        Self::clean()
+    }
+}
+
+impl GarbageRules {
+    /// Every attack comes in with a single hole column.
+    pub fn clean() -> Self {
+        Self {
+            attack: AttackTable::default(),
+            cancelling: true,
+            entry_delay_ms: 500,
+            line_messiness: 0,
+            attack_messiness: 100,
+            initial_rows: 0,
+        }
+    }
+
+    /// Every line has its hole in another column.
+    pub fn cheese() -> Self {
+        Self {
+            line_messiness: 100,
+            ..Self::clean()
+        }
+    }
+}
+
+/// Hole column for garbage line number `line_idx`, derived from the game
+/// seed so replays build the same board. The first line ever always picks
+/// a random column, later ones move away from `current` with a chance of
+/// `messiness` percent.
+pub fn next_garbage_hole(
+    seed: &GameSeed,
+    line_idx: u16,
+    current: Option<i8>,
+    messiness: u8,
+    width: i8,
+) -> i8 {
+    let mut seed = *seed;
+    for (s, b) in seed.iter_mut().zip(line_idx.to_le_bytes()) {
+        *s ^= b;
+    }
+    let mut rng = get_rng(&seed);
+    let roll: u8 = rng.random_range(0..100);
+    match current {
+        None => rng.random_range(0..width),
+        Some(hole) if roll >= messiness || width < 2 => hole,
+        Some(hole) => {
+            // any column but the current one
+            let x = rng.random_range(0..width - 1);
+            if x >= hole {
+                x + 1
+            } else {
+                x
+            }
+        }
+    }
+}
+
+const GARBAGE_QUEUE_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingGarbage {
+    pub lines: u16,
+    /// event time the garbage was received
+    pub received: i64,
+}
+
+/// Received garbage that did not enter the board yet, oldest first.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GarbageQueue {
+    entries: [PendingGarbage; GARBAGE_QUEUE_LEN],
+    len: u8,
+}
+
+impl GarbageQueue {
+    pub fn empty() -> Self {
+        Self {
+            entries: [PendingGarbage {
+                lines: 0,
+                received: 0,
+            }; GARBAGE_QUEUE_LEN],
+            len: 0,
+        }
+    }
+
+    pub fn entries(&self) -> &[PendingGarbage] {
+        &self.entries[..self.len as usize]
+    }
+
+    pub fn total(&self) -> u16 {
+        self.entries().iter().map(|e| e.lines).sum()
+    }
+
+    pub fn push(&mut self, lines: u16, received: i64) {
+        if lines == 0 {
+            return;
+        }
+        if self.len as usize == GARBAGE_QUEUE_LEN {
+            // out of room: the newest entry grows instead
+            self.entries[GARBAGE_QUEUE_LEN - 1].lines += lines;
+            return;
+        }
+        self.entries[self.len as usize] = PendingGarbage { lines, received };
+        self.len += 1;
+    }
+
+    /// Cancel up to `lines` of the oldest garbage, returns how many were
+    /// cancelled.
+    pub fn cancel(&mut self, lines: u16) -> u16 {
+        let mut cancelled = 0;
+        while self.len > 0 && cancelled < lines {
+            let front = &mut self.entries[0];
+            let n = front.lines.min(lines - cancelled);
+            front.lines -= n;
+            cancelled += n;
+            if front.lines == 0 {
+                self.pop_front();
+            }
+        }
+        cancelled
+    }
+
+    /// Remove the oldest attack if its entry delay ran out, returns its
+    /// lines.
+    pub fn pop_ready(&mut self, entry_delay_ms: u16, event_time: i64) -> Option<u16> {
+        let front = *self.entries().first()?;
+        if front.received + entry_delay_ms as i64 > event_time {
+            return None;
+        }
+        self.pop_front();
+        Some(front.lines)
+    }
+
+    fn pop_front(&mut self) {
+        self.entries.copy_within(1..self.len as usize, 0);
+        self.len -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        segments_to_states, BoardMatrix, CellValue, GameRules, GameState, RotState,
+        Tet, TetAction,
+    };
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn clear(lines: u16, spin: SpinKind) -> LineClear {
+        LineClear {
+            lines,
+            spin,
+            perfect_clear: false,
+            b2b: false,
+            combo: 0,
+            level: 1,
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn attack_tables() {
+        let old = AttackTable::default();
+        assert_eq!(old.attack(&clear(4, SpinKind::None)), 4);
+        assert_eq!(old.attack(&clear(2, SpinKind::Mini)), 3);
+        assert_eq!(old.attack(&clear(0, SpinKind::Full)), 0);
+        let guideline = AttackTable::guideline();
+        let b2b_tsd = LineClear {
+            b2b: true,
+            combo: 3,
+            ..clear(2, SpinKind::Full)
+        };
+        assert_eq!(guideline.attack(&b2b_tsd), 4 + 2 + 1);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn queue_cancels_oldest_first() {
+        let mut q = GarbageQueue::empty();
+        q.push(2, 0);
+        q.push(3, 100);
+        assert_eq!(q.cancel(3), 3);
+        assert_eq!(q.entries().len(), 1);
+        assert_eq!(q.total(), 2);
+        assert_eq!(q.cancel(5), 2);
+        assert_eq!(q.total(), 0);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn queue_waits_for_entry_delay() {
+        let mut q = GarbageQueue::empty();
+        q.push(2, 0);
+        q.push(3, 100);
+        assert_eq!(q.pop_ready(500, 499), None);
+        assert_eq!(q.pop_ready(500, 650), Some(2));
+        assert_eq!(q.pop_ready(500, 650), Some(3));
+        assert_eq!(q.pop_ready(500, 650), None);
+    }
+
+    /// A tetris ready to be hard dropped, with one extra block so it is
+    /// not a perfect clear.
+    fn tetris_ready(rules: GameRules) -> GameState {
+        let mut state = GameState::new_with_rules(&[0; 32], 0, rules);
+        state.main_board = BoardMatrix::empty();
+        for y in 0..4 {
+            for x in 0..9 {
+                state.main_board.set_cell(y, x, CellValue::Garbage);
+            }
+        }
+        state.main_board.set_cell(4, 0, CellValue::Garbage);
+        let mut pcs = state.current_pcs.unwrap();
+        pcs.tet = Tet::I;
+        pcs.rs = RotState::R1;
+        pcs.pos = (10, 7);
+        state.main_board.spawn_piece(&pcs).unwrap();
+        state.current_pcs = Some(pcs);
+        state
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn attack_cancels_pending_garbage() {
+        let mut state = tetris_ready(GameRules::default());
+        state.apply_raw_received_garbage(3);
+        assert_eq!(state.pending_garbage(), 3);
+        let state = state.try_action(TetAction::HardDrop, 10).unwrap();
+        assert_eq!(state.total_lines, 4);
+        assert_eq!(state.garbage_cancelled, 3);
+        assert_eq!(state.total_garbage_sent, 1);
+        assert_eq!(state.pending_garbage(), 0);
+        assert_eq!(state.garbage_applied, 0);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn garbage_enters_after_entry_delay() {
+        let mut rules = GameRules::default();
+        rules.garbage.cancelling = false;
+        let mut state = tetris_ready(rules);
+        state.apply_raw_received_garbage(3);
+        let state = state.try_action(TetAction::HardDrop, 10).unwrap();
+        assert_eq!(state.total_garbage_sent, 4);
+        assert_eq!(state.garbage_queue.entries()[0].received, 10);
+        // still waiting when the next piece locks too early
+        let state = state.try_action(TetAction::HardDrop, 500).unwrap();
+        assert_eq!(state.pending_garbage(), 3);
+        let state = state.try_action(TetAction::HardDrop, 510).unwrap();
+        assert_eq!(state.pending_garbage(), 0);
+        assert_eq!(state.garbage_applied, 3);
+    }
+
+    fn garbage_holes(garbage: GarbageRules, seed: u8) -> Vec<i8> {
+        let rules = GameRules {
+            garbage: GarbageRules {
+                entry_delay_ms: 0,
+                ..garbage
+            },
+            ..Default::default()
+        };
+        let mut state = GameState::new_with_rules(&[seed; 32], 0, rules);
+        state.apply_raw_received_garbage(8);
+        let state = state.try_action(TetAction::HardDrop, 1).unwrap();
+        assert_eq!(state.garbage_applied, 8);
+        (0..8)
+            .map(|y| {
+                (0..10)
+                    .find(|x| {
+                        state.main_board.get_cell(y, *x) == Some(CellValue::Empty)
+                    })
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn clean_garbage_keeps_the_hole_and_cheese_moves_it() {
+        for seed in 0..10 {
+            let clean = garbage_holes(GarbageRules::clean(), seed);
+            assert!(clean.iter().all(|x| *x == clean[0]));
+            let cheese = garbage_holes(GarbageRules::cheese(), seed);
+            assert!(cheese.windows(2).all(|w| w[0] != w[1]));
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn replay_rebuilds_messy_garbage() {
+        let rules = GameRules {
+            garbage: GarbageRules {
+                line_messiness: 50,
+                attack_messiness: 50,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut state = GameState::new_with_rules(&[7; 32], 0, rules);
+        let mut segments = vec![state.last_segment];
+        for i in 0..20 {
+            if i % 3 == 0 {
+                state.apply_raw_received_garbage(state.garbage_recv + i % 4 + 1);
+            }
+            let t = i as i64 * 300;
+            for action in [TetAction::MoveLeft, TetAction::HardDrop] {
+                if let Ok(new_state) = state.try_action(action, t) {
+                    state = new_state;
+                    segments.push(state.last_segment);
+                }
+            }
+        }
+        assert!(state.garbage_applied > 0);
+        let replayed = segments_to_states(&segments);
+        assert_eq!(replayed.last().unwrap().main_board, state.main_board);
+    }
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+use super::game_state::{GameReplayInit, GameReplaySegment, GameState};
+
+/// Segments between two keyframes of a replay.
+pub const REPLAY_KEYFRAME_INTERVAL: usize = 64;
+
+/// Replay that keeps a snapshot every `interval` segments instead of every
+/// state. Seeking restores the keyframe before the wanted state and plays
+/// the segments after it, so long games cost one state per `interval`
+/// segments in memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyframedReplay {
+    segments: Vec<GameReplaySegment>,
+    /// `keyframes[k]` is the state after segment `k * interval`
+    keyframes: Vec<GameState>,
+    interval: usize,
+}
+
+impl KeyframedReplay {
+    /// Simulates the segments once to take the keyframes.
+    pub fn from_segments(
+        segments: Vec<GameReplaySegment>,
+        interval: usize,
+    ) -> anyhow::Result<Self> {
+        let interval = interval.max(1);
+        let Some(GameReplaySegment::Init(init)) = segments.first() else {
+            anyhow::bail!("replay does not start with an init segment");
+        };
+        let mut state = GameState::from_init(init);
+        let mut keyframes = vec![state];
+        for (i, segment) in segments.iter().enumerate().skip(1) {
+            state.accept_segment(segment)?;
+            if i % interval == 0 {
+                keyframes.push(state);
+            }
+        }
+        Ok(Self {
+            segments,
+            keyframes,
+            interval,
+        })
+    }
+
+    /// Replay of the states a client sent for one game, in order. Games
+    /// saved without their first state start from an empty init segment.
+    pub fn from_saved_states(
+        states: &[GameState],
+        interval: usize,
+    ) -> anyhow::Result<Self> {
+        let Some(first) = states.first() else {
+            anyhow::bail!("no saved states");
+        };
+        let mut segments = vec![];
+        if !matches!(first.last_segment, GameReplaySegment::Init(_)) {
+            segments.push(GameReplaySegment::Init(GameReplayInit::empty(
+                &first.init_seed,
+                first.start_time,
+                first.rules,
+            )));
+        }
+        segments.extend(states.iter().map(|s| s.last_segment));
+        // states that only received garbage or a result repeat the segment
+        segments.dedup();
+        if let Some(reason) = states.last().and_then(|s| s.game_over_reason) {
+            if segments.last() != Some(&GameReplaySegment::GameOver(reason)) {
+                segments.push(GameReplaySegment::GameOver(reason));
+            }
+        }
+        Self::from_segments(segments, interval)
+    }
+
+    /// Number of states, the init state included.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn segments(&self) -> &[GameReplaySegment] {
+        &self.segments
+    }
+
+    /// State after segment `idx`, clamped to the last state.
+    pub fn seek(&self, idx: usize) -> anyhow::Result<GameState> {
+        let idx = idx.min(self.len() - 1);
+        let k = idx / self.interval;
+        let mut state = self.keyframes[k];
+        for segment in &self.segments[k * self.interval + 1..=idx] {
+            state.accept_segment(segment)?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{segments_to_states, GameOverReason, TetAction};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn play_game() -> Vec<GameState> {
+        let mut state = GameState::new(&[8; 32], 0);
+        let mut states = vec![state];
+        for i in 0..60 {
+            let action = [
+                TetAction::MoveLeft,
+                TetAction::MoveRight,
+                TetAction::RotateRight,
+                TetAction::HardDrop,
+            ][i % 4];
+            if let Ok(s) = state.try_action(action, i as i64 * 30) {
+                state = s;
+                states.push(state);
+            }
+        }
+        states
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn seek_matches_full_simulation() {
+        let states = play_game();
+        let segments: Vec<_> = states.iter().map(|s| s.last_segment).collect();
+        let all = segments_to_states(&segments);
+        let replay = KeyframedReplay::from_segments(segments, 8).unwrap();
+        assert_eq!(replay.len(), all.len());
+        assert_eq!(replay.keyframes.len(), (all.len() - 1) / 8 + 1);
+        for i in [0, 1, 7, 8, 9, 16, all.len() - 1] {
+            assert_eq!(replay.seek(i).unwrap(), all[i]);
+        }
+        assert_eq!(replay.seek(10_000).unwrap(), *all.last().unwrap());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn saved_states_with_repeats() {
+        let mut states = play_game();
+        let mut with_garbage = *states.last().unwrap();
+        with_garbage.apply_raw_received_garbage(2);
+        let mut won = with_garbage;
+        won.game_over_reason = Some(GameOverReason::Win);
+        states.extend([with_garbage, won]);
+
+        let replay =
+            KeyframedReplay::from_saved_states(&states[1..], REPLAY_KEYFRAME_INTERVAL)
+                .unwrap();
+        let last = replay.seek(replay.len() - 1).unwrap();
+        assert_eq!(last.game_over_reason, Some(GameOverReason::Win));
+        assert_eq!(last.main_board, won.main_board);
+        assert_eq!(last.score, won.score);
+    }
+}
@@ -0,0 +1,314 @@
+//! The engine before rules were versioned, `RulesVersion::V0`. Games saved
+//! back then have no rules recorded; they replay with `GameRules::legacy()`
+//! and the scoring, spins and garbage below, and their database rows are
+//! read with `LegacyGameState`.
+
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use super::{
+    game_state::{
+        CurrentPcsInfo, GameOverReason, GameReplayInit, GameReplaySegment,
+        GameReplaySlice, GameState, HoldPcsInfo,
+    },
+    matrix::{BoardMatrix, CellValue},
+    random::GameSeed,
+    rot::RotState,
+    rot_system::RotationSystemKind,
+    rules::GameRules,
+    tetpcs::{Tet, TetAction},
+};
+
+/// Points for a line clear. A T piece with three blocked corners has both
+/// spin flags set and scores both tables.
+pub fn clear_points(
+    lines: u16,
+    t_spin: bool,
+    t_mini_spin: bool,
+    perfect_clear: bool,
+    combo: i8,
+) -> i32 {
+    let mut score = match lines {
+        1 => 40,
+        2 => 80,
+        3 => 160,
+        4 => 320,
+        _ => 0,
+    };
+    if perfect_clear {
+        score += match lines {
+            1 => 200,
+            2 => 400,
+            3 => 800,
+            4 => 1600,
+            _ => 0,
+        };
+    }
+    if t_spin {
+        score += match lines {
+            1 => 1000,
+            2 => 2000,
+            3 => 3000,
+            _ => 0,
+        };
+    }
+    if t_mini_spin {
+        score += match lines {
+            1 => 666,
+            2 => 1666,
+            3 => 2666,
+            _ => 0,
+        };
+    }
+    if combo > 0 {
+        score += 50 * combo as i32;
+    }
+    score
+}
+
+/// Points for each row a piece falls by gravity or soft drop.
+pub const SOFT_DROP_POINTS: i32 = 2;
+/// Points for a hard drop, however far it goes.
+pub const HARD_DROP_POINTS: i32 = 10;
+
+/// Spin flags `(t_spin, t_mini_spin)` after `pcs` rotated into place with
+/// `kick`. A T piece counts its blocked box corners, the walls do not
+/// count; any other piece spins when it was kicked.
+pub fn detect_spin(
+    board: &BoardMatrix,
+    pcs: &CurrentPcsInfo,
+    kick: (i8, i8),
+) -> (bool, bool) {
+    if pcs.tet != Tet::T {
+        return (kick != (0, 0), false);
+    }
+    let (y, x) = pcs.pos;
+    let blocked = [(0, 0), (0, 2), (2, 0), (2, 2)]
+        .into_iter()
+        .filter(|(dy, dx)| {
+            matches!(
+                board.get_cell(y + dy, x + dx),
+                Some(CellValue::Piece(_)) | Some(CellValue::Garbage)
+            )
+        })
+        .count();
+    (blocked >= 3, blocked >= 2)
+}
+
+/// Board rows as saved: two cells per byte, the first one in the high
+/// nibble.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyBoardMatrix {
+    #[serde_as(as = "[_; 40]")]
+    rows: [[u8; 5]; 40],
+}
+
+impl LegacyBoardMatrix {
+    fn cell(&self, y: usize, x: usize) -> CellValue {
+        let byte = self.rows[y][x / 2];
+        match [byte >> 4, byte & 0xf][x % 2] {
+            1 => CellValue::Piece(Tet::I),
+            2 => CellValue::Piece(Tet::L),
+            3 => CellValue::Piece(Tet::J),
+            4 => CellValue::Piece(Tet::T),
+            5 => CellValue::Piece(Tet::S),
+            6 => CellValue::Piece(Tet::Z),
+            7 => CellValue::Piece(Tet::O),
+            8 => CellValue::Garbage,
+            9 => CellValue::Ghost,
+            _ => CellValue::Empty,
+        }
+    }
+
+    pub fn to_board(self) -> BoardMatrix {
+        let mut board = BoardMatrix::empty();
+        for y in 0..40 {
+            for x in 0..10 {
+                board.set_cell(y as i8, x as i8, self.cell(y, x));
+            }
+        }
+        board
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyCurrentPcsInfo {
+    pub pos: (i8, i8),
+    pub tet: Tet,
+    pub rs: RotState,
+    pub id: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyGameReplayInit {
+    pub init_seed: GameSeed,
+    pub start_time: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegacyGameReplaySegment {
+    Init(LegacyGameReplayInit),
+    Update(GameReplaySlice),
+    GameOver(GameOverReason),
+}
+
+impl LegacyGameReplaySegment {
+    pub fn to_segment(self) -> GameReplaySegment {
+        match self {
+            Self::Init(init) => GameReplaySegment::Init(GameReplayInit::empty(
+                &init.init_seed,
+                init.start_time,
+                GameRules::legacy(),
+            )),
+            Self::Update(slice) => GameReplaySegment::Update(slice),
+            Self::GameOver(reason) => GameReplaySegment::GameOver(reason),
+        }
+    }
+}
+
+/// `GameState` as it was saved with `data_version` 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyGameState {
+    pub score: i32,
+    pub is_t_spin: bool,
+    pub is_t_mini_spin: bool,
+    pub is_b2b: bool,
+    pub combo_counter: i8,
+    pub main_board: LegacyBoardMatrix,
+    pub last_action: TetAction,
+    pub current_pcs: Option<LegacyCurrentPcsInfo>,
+    pub current_id: u16,
+    pub hold_pcs: Option<HoldPcsInfo>,
+    pub game_over_reason: Option<GameOverReason>,
+    pub seed: GameSeed,
+    pub init_seed: GameSeed,
+    pub start_time: i64,
+    pub total_lines: u16,
+    pub total_garbage_sent: u16,
+    pub garbage_recv: u16,
+    pub garbage_applied: u16,
+    pub total_moves: u16,
+    pub last_segment: LegacyGameReplaySegment,
+    pub last_segment_idx: u16,
+    pub next_pcs_bags: [Tet; 14],
+    pub next_pcs_idx: u8,
+}
+
+impl From<LegacyGameState> for GameState {
+    /// The same game, playing on with `GameRules::legacy()`. Statistics
+    /// start counting from here.
+    fn from(old: LegacyGameState) -> Self {
+        let mut state = GameState::new_with_rules(
+            &old.init_seed,
+            old.start_time,
+            GameRules::legacy(),
+        );
+        state.score = old.score;
+        state.is_t_spin = old.is_t_spin;
+        state.is_t_mini_spin = old.is_t_mini_spin;
+        state.is_b2b = old.is_b2b;
+        state.combo_counter = old.combo_counter;
+        state.main_board = old.main_board.to_board();
+        state.last_action = old.last_action;
+        state.current_pcs = old.current_pcs.map(|p| CurrentPcsInfo {
+            pos: p.pos,
+            tet: p.tet,
+            rs: p.rs,
+            id: p.id,
+            rot_system: RotationSystemKind::Srs,
+        });
+        state.current_id = old.current_id;
+        state.hold_pcs = old.hold_pcs;
+        state.game_over_reason = old.game_over_reason;
+        state.seed = old.seed;
+        state.total_lines = old.total_lines;
+        state.total_garbage_sent = old.total_garbage_sent;
+        state.garbage_recv = old.garbage_recv;
+        state.garbage_applied = old.garbage_applied;
+        state.total_moves = old.total_moves;
+        state.last_segment = old.last_segment.to_segment();
+        if let LegacyGameReplaySegment::Update(slice) = old.last_segment {
+            state.last_event_time = slice.event_timestamp;
+        }
+        state.last_segment_idx = old.last_segment_idx;
+        state.next_pcs_bags[..14].copy_from_slice(&old.next_pcs_bags);
+        state.next_pcs_idx = old.next_pcs_idx;
+        state.next_pcs_len = 14;
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{segments_to_states, RulesVersion};
+    use super::*;
+    use base64::Engine;
+    use wasm_bindgen_test::*;
+
+    /// `game_states.state_data` of every move of a game saved before rules
+    /// were versioned: it receives garbage twice, holds, soft drops, and
+    /// rotates and shifts pieces on the floor.
+    const SAVED_ROWS: &str = include_str!("testdata/legacy_game_states.txt");
+
+    fn saved_states() -> Vec<GameState> {
+        SAVED_ROWS
+            .lines()
+            .map(|row| {
+                let bytes = base64::prelude::BASE64_URL_SAFE.decode(row).unwrap();
+                postcard::from_bytes::<LegacyGameState>(&bytes)
+                    .unwrap()
+                    .into()
+            })
+            .collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn saved_rows_decode() {
+        let states = saved_states();
+        let last = states.last().unwrap();
+        assert_eq!(states.len(), 86);
+        assert_eq!(last.rules.version, RulesVersion::V0);
+        assert_eq!(last.score, 200);
+        assert_eq!(last.total_lines, 1);
+        assert_eq!(last.garbage_recv, 3);
+        assert_eq!(last.main_board.count_garbage_rows(), 3);
+    }
+
+    /// The board without the ghost piece, which only ever was drawn.
+    fn solid_rows(state: &GameState) -> Vec<Vec<CellValue>> {
+        let mut rows = state.main_board.rows();
+        for cell in rows.iter_mut().flatten() {
+            if *cell == CellValue::Ghost {
+                *cell = CellValue::Empty;
+            }
+        }
+        rows
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn baseline_game_replays_the_same() {
+        let saved = saved_states();
+        let segments: Vec<_> = saved.iter().map(|s| s.last_segment).collect();
+        let replayed = segments_to_states(&segments);
+        assert_eq!(replayed.len(), saved.len());
+        for (i, (replayed, saved)) in replayed.iter().zip(&saved).enumerate() {
+            assert_eq!(solid_rows(replayed), solid_rows(saved), "move {i}");
+            assert_eq!(replayed.current_pcs, saved.current_pcs, "move {i}");
+            assert_eq!(replayed.hold_pcs, saved.hold_pcs, "move {i}");
+            assert_eq!(replayed.get_next_pcs(), saved.get_next_pcs(), "move {i}");
+            assert_eq!(replayed.seed, saved.seed, "move {i}");
+            assert_eq!(replayed.score, saved.score, "move {i}");
+            assert_eq!(replayed.total_lines, saved.total_lines, "move {i}");
+            assert_eq!(replayed.combo_counter, saved.combo_counter, "move {i}");
+            assert_eq!(replayed.is_b2b, saved.is_b2b, "move {i}");
+            assert_eq!(
+                replayed.total_garbage_sent, saved.total_garbage_sent,
+                "move {i}"
+            );
+            assert_eq!(replayed.garbage_applied, saved.garbage_applied, "move {i}");
+        }
+    }
+}
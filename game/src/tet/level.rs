@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How fast pieces fall at a given level.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Default,
+)]
+pub enum GravityCurve {
+    /// no progression, use the player's `auto_softdrop_interval` setting
+    #[default]
+    Constant,
+    /// guideline formula, 20G from level 20
+    Guideline,
+    /// NES frame table at 60.0988 fps
+    Nes,
+    /// pieces drop to the floor as soon as they spawn or move
+    TwentyG,
+}
+
+impl GravityCurve {
+    /// Time between two gravity steps, `None` for the player's setting.
+    pub fn interval(&self, level: u16) -> Option<Duration> {
+        match self {
+            Self::Constant => None,
+            Self::Guideline => {
+                let l = level.clamp(1, 20) as i32 - 1;
+                let seconds = (0.8 - l as f64 * 0.007).powi(l);
+                Some(Duration::from_secs_f64(seconds))
+            }
+            Self::Nes => {
+                let frames = match level {
+                    0 => 48,
+                    1 => 43,
+                    2 => 38,
+                    3 => 33,
+                    4 => 28,
+                    5 => 23,
+                    6 => 18,
+                    7 => 13,
+                    8 => 8,
+                    9 => 6,
+                    10..=12 => 5,
+                    13..=15 => 4,
+                    16..=18 => 3,
+                    19..=28 => 2,
+                    _ => 1,
+                };
+                Some(Duration::from_secs_f64(frames as f64 / 60.0988))
+            }
+            Self::TwentyG => Some(Duration::from_millis(16)),
+        }
+    }
+
+    pub fn is_20g(&self, level: u16) -> bool {
+        match self {
+            Self::Guideline => level >= 20,
+            Self::TwentyG => true,
+            Self::Constant | Self::Nes => false,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct LevelRules {
+    pub start_level: u16,
+    /// 0 keeps the start level for the whole game
+    pub lines_per_level: u16,
+    pub gravity: GravityCurve,
+}
+
+impl Default for LevelRules {
+    fn default() -> Self {
+        Self {
+            start_level: 1,
+            lines_per_level: 10,
+            gravity: GravityCurve::default(),
+        }
+    }
+}
+
+impl LevelRules {
+    pub fn level_for_lines(&self, total_lines: u16) -> u16 {
+        if self.lines_per_level == 0 {
+            return self.start_level;
+        }
+        self.start_level + total_lines / self.lines_per_level
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+/// Guideline lock delay: a grounded piece locks `duration_ms` after it
+/// touched down. Moving or rotating restarts the timer, at most
+/// `max_move_resets` times; with `step_reset` the counter starts over every
+/// time the piece reaches a new lowest row. A zero duration locks on the
+/// first gravity tick after landing.
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct LockDelayRules {
+    pub duration_ms: u16,
+    pub max_move_resets: u8,
+    pub step_reset: bool,
+}
+
+impl Default for LockDelayRules {
+    fn default() -> Self {
+        Self {
+            duration_ms: 500,
+            max_move_resets: 15,
+            step_reset: true,
+        }
+    }
+}
+
+impl LockDelayRules {
+    pub fn disabled() -> Self {
+        Self {
+            duration_ms: 0,
+            max_move_resets: 0,
+            step_reset: false,
+        }
+    }
+}
+
+/// Lock delay bookkeeping for the current piece.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockDelayState {
+    /// event time the timer (re)started, if the piece is on the ground
+    pub started: Option<i64>,
+    pub move_resets: u8,
+    pub lowest_row: i8,
+}
+
+impl LockDelayState {
+    pub fn new(spawn_row: i8) -> Self {
+        Self {
+            started: None,
+            move_resets: 0,
+            lowest_row: spawn_row,
+        }
+    }
+
+    /// Time when the piece locks, if the timer is running.
+    pub fn deadline(&self, rules: &LockDelayRules) -> Option<i64> {
+        self.started.map(|t| t + rules.duration_ms as i64)
+    }
+
+    pub fn is_expired(&self, rules: &LockDelayRules, event_time: i64) -> bool {
+        self.deadline(rules).is_some_and(|t| t <= event_time)
+    }
+
+    /// Update after an action on the same piece.
+    pub fn update(
+        &mut self,
+        rules: &LockDelayRules,
+        row: i8,
+        moved: bool,
+        grounded: bool,
+        event_time: i64,
+    ) {
+        if row < self.lowest_row {
+            self.lowest_row = row;
+            if rules.step_reset {
+                self.move_resets = 0;
+            }
+        }
+        let can_reset = self.move_resets < rules.max_move_resets;
+        if moved && self.started.is_some() && can_reset {
+            self.move_resets += 1;
+            self.started = None;
+        }
+        if grounded {
+            if self.started.is_none() {
+                self.started = Some(event_time);
+            }
+        } else if can_reset {
+            // out of resets the timer keeps running in the air, so the
+            // piece locks as soon as it lands again
+            self.started = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{GameRules, GameState, TetAction};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn grounded_state(rules: GameRules) -> GameState {
+        let mut state = GameState::new_with_rules(&[0; 32], 0, rules);
+        while let Ok(s) = state.try_action(TetAction::UserSoftDrop, 0) {
+            state = s;
+        }
+        state
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn piece_locks_after_delay() {
+        let state = grounded_state(GameRules::default());
+        let id = state.current_pcs.unwrap().id;
+        assert_eq!(state.lock_delay.started, Some(0));
+        assert!(state.try_action(TetAction::AutoSoftDrop, 499).is_err());
+        let state = state.try_action(TetAction::AutoSoftDrop, 500).unwrap();
+        assert_ne!(state.current_pcs.unwrap().id, id);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn disabled_lock_delay_locks_on_next_gravity_tick() {
+        let rules = GameRules {
+            lock_delay: LockDelayRules::disabled(),
+            ..Default::default()
+        };
+        let state = grounded_state(rules);
+        let id = state.current_pcs.unwrap().id;
+        let state = state.try_action(TetAction::AutoSoftDrop, 0).unwrap();
+        assert_ne!(state.current_pcs.unwrap().id, id);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn move_resets_are_limited() {
+        let rules = GameRules::default();
+        let mut state = grounded_state(rules);
+        let id = state.current_pcs.unwrap().id;
+        let mut t = 0;
+        for i in 0..rules.lock_delay.max_move_resets {
+            t += 400;
+            let action = [TetAction::MoveLeft, TetAction::MoveRight][i as usize % 2];
+            state = state.try_action(action, t).unwrap();
+            assert_eq!(state.lock_delay.started, Some(t));
+        }
+        // out of resets: moving does not restart the timer any more
+        state = state.try_action(TetAction::MoveLeft, t + 400).unwrap();
+        assert_eq!(state.lock_delay.started, Some(t));
+        // and the piece is locked as soon as the delay runs out
+        state = state.try_action(TetAction::MoveRight, t + 500).unwrap();
+        assert_ne!(state.current_pcs.unwrap().id, id);
+    }
+}
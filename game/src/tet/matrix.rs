@@ -1,9 +1,15 @@
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{Error as _, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 use super::random::{get_determinist_val, GameSeed};
 
-use super::{game_state::CurrentPcsInfo, rot::RotState, tetpcs::Tet};
-use serde_with::serde_as;
+use super::{
+    bitboard::piece_mask, game_state::CurrentPcsInfo, rot::RotState,
+    rot_system::RotationSystemKind, tetpcs::Tet,
+};
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -18,7 +24,7 @@ pub enum CellValue {
 impl CellValue {
     // This has to be a const fn
     #[inline(always)]
-    const fn into_bits(self) -> u8 {
+    pub(crate) const fn into_bits(self) -> u8 {
         match self {
             CellValue::Empty => 0,
             CellValue::Piece(Tet::I) => 1,
@@ -33,7 +39,7 @@ impl CellValue {
         }
     }
     #[inline(always)]
-    const fn from_bits(value: u8) -> Self {
+    pub(crate) const fn from_bits(value: u8) -> Self {
         match value {
             0 => CellValue::Empty,
             1 => CellValue::Piece(Tet::I),
@@ -48,16 +54,72 @@ impl CellValue {
             _ => CellValue::Empty,
         }
     }
+    /// Pieces and garbage; ghosts do not block anything.
+    #[inline(always)]
+    pub const fn is_solid(self) -> bool {
+        matches!(self, CellValue::Piece(_) | CellValue::Garbage)
+    }
 }
 
-#[serde_as]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct BoardMatrix<const R: usize = 40, const C: usize = 10> {
     // 400 * cellValue = 1600bit after / 8000 before -- 200byte after, 1k before
     // with no color -- 400bit = 80bytes
-    #[serde_as(as = "[_; R]")]
-    vv: [CellValueRow10; R],
+    vv: [CellValueRow; R],
+    /// occupancy bitboard: bit x of row y is set for solid cells. Follows
+    /// `vv`, which keeps the colours; only `vv` is serialized.
+    bits: [u16; R],
+}
+
+/// Serialized as `ceil(C / 2)` bytes per row, two cells each, so boards
+/// only pay for their own width; the bitboard is rebuilt on load.
+impl<const R: usize, const C: usize> Serialize for BoardMatrix<R, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(R * Self::ROW_BYTES)?;
+        for row in &self.vv {
+            for pair in &row.v_r[..Self::ROW_BYTES] {
+                tuple.serialize_element(&pair.into_bits())?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const R: usize, const C: usize> Deserialize<'de> for BoardMatrix<R, C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_tuple(R * Self::ROW_BYTES, BoardMatrixVisitor)
+    }
 }
+
+struct BoardMatrixVisitor<const R: usize, const C: usize>;
+
+impl<'de, const R: usize, const C: usize> Visitor<'de> for BoardMatrixVisitor<R, C> {
+    type Value = BoardMatrix<R, C>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} bytes of board cells",
+            R * BoardMatrix::<R, C>::ROW_BYTES
+        )
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut board = BoardMatrix::<R, C>::empty();
+        for y in 0..R {
+            for i in 0..BoardMatrix::<R, C>::ROW_BYTES {
+                let Some(byte) = seq.next_element::<u8>()? else {
+                    let read = y * BoardMatrix::<R, C>::ROW_BYTES + i;
+                    return Err(A::Error::invalid_length(read, &self));
+                };
+                board.vv[y].v_r[i] = CellValuePairByte::from_bits(byte);
+            }
+            board.bits[y] = board.vv[y].solid_bits();
+        }
+        Ok(board)
+    }
+}
+
 impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     pub const fn c_num_rows() -> usize {
         R
@@ -65,8 +127,16 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     pub const fn c_num_cols() -> usize {
         C
     }
+    /// Bytes of a serialized row.
+    const ROW_BYTES: usize = {
+        assert!(C <= MAX_BOARD_WIDTH, "board wider than MAX_BOARD_WIDTH");
+        C.div_ceil(2)
+    };
 }
 
+/// Widest board a row can hold in memory; rows are packed 2 cells per
+/// byte, and serialized with only the bytes of their board's width.
+pub const MAX_BOARD_WIDTH: usize = 16;
 pub const SIDE_BOARD_WIDTH: usize = 4;
 pub type BoardMatrixHold = BoardMatrix<3, SIDE_BOARD_WIDTH>;
 pub type BoardMatrixNext = BoardMatrix<16, SIDE_BOARD_WIDTH>;
@@ -104,63 +174,77 @@ impl CellValuePairByte {
     }
 }
 
-#[serde_as]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub struct CellValueRow10 {
-    #[serde_as(as = "[_; 5]")]
-    v_r: [CellValuePairByte; 5],
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct CellValueRow {
+    v_r: [CellValuePairByte; MAX_BOARD_WIDTH / 2],
 }
 
-impl CellValueRow10 {
+impl CellValueRow {
     #[inline(always)]
     fn empty() -> Self {
         Self {
-            v_r: [CellValuePairByte::empty(); 5],
+            v_r: [CellValuePairByte::empty(); MAX_BOARD_WIDTH / 2],
         }
     }
     #[inline(always)]
     fn get(&self, idx: i8) -> CellValue {
-        assert!((0..=9).contains(&idx), "bad idx: {idx} expected: 0..=4");
+        assert!(
+            (0..MAX_BOARD_WIDTH as i8).contains(&idx),
+            "bad idx: {idx} expected: 0..{MAX_BOARD_WIDTH}"
+        );
         self.v_r[idx as usize / 2].get(idx % 2)
     }
     #[inline(always)]
     fn set(&mut self, idx: i8, new: CellValue) {
-        assert!((0..=9).contains(&idx), "bad idx: {idx} expected: 0..=4");
+        assert!(
+            (0..MAX_BOARD_WIDTH as i8).contains(&idx),
+            "bad idx: {idx} expected: 0..{MAX_BOARD_WIDTH}"
+        );
         self.v_r[idx as usize / 2].set(idx % 2, new);
     }
+    fn solid_bits(&self) -> u16 {
+        let mut bits = 0;
+        for x in 0..MAX_BOARD_WIDTH {
+            if self.get(x as i8).is_solid() {
+                bits |= 1 << x;
+            }
+        }
+        bits
+    }
     #[inline(always)]
-    fn to_cells(self) -> [CellValue; 10] {
-        [
-            self.v_r[0].get(0),
-            self.v_r[0].get(1),
-            self.v_r[1].get(0),
-            self.v_r[1].get(1),
-            self.v_r[2].get(0),
-            self.v_r[2].get(1),
-            self.v_r[3].get(0),
-            self.v_r[3].get(1),
-            self.v_r[4].get(0),
-            self.v_r[4].get(1),
-        ]
+    fn to_cells(self) -> [CellValue; MAX_BOARD_WIDTH] {
+        let mut cells = [CellValue::Empty; MAX_BOARD_WIDTH];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            *cell = self.get(i as i8);
+        }
+        cells
     }
 }
 
 impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     pub fn clear_line(&mut self, line: i8) {
-        for i in line..39 {
-            self.vv[i as usize] = self.vv[i as usize + 1];
+        for i in (line as usize)..(R - 1) {
+            self.vv[i] = self.vv[i + 1];
+            self.bits[i] = self.bits[i + 1];
         }
+        self.vv[R - 1] = CellValueRow::empty();
+        self.bits[R - 1] = 0;
     }
     pub fn inject_single_garbage_line(&mut self, seed: GameSeed) {
         let v: u8 = get_determinist_val::<u8>(&seed) % C as u8;
-
+        self.inject_garbage_line(v as i8);
+    }
+    /// Push everything up one row and fill the bottom one, leaving a hole
+    /// at column `hole`.
+    pub fn inject_garbage_line(&mut self, hole: i8) {
         // move all things up
-        for i in (0..(R - 2)).rev() {
+        for i in (0..(R - 1)).rev() {
             self.vv[i + 1] = self.vv[i];
+            self.bits[i + 1] = self.bits[i];
         }
 
         for x in 0..(C as i8) {
-            if x != (v as i8) {
+            if x != hole {
                 self.set_cell(0, x, CellValue::Garbage)
             } else {
                 self.set_cell(0, x, CellValue::Empty)
@@ -179,6 +263,102 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     #[inline(always)]
     pub fn set_cell(&mut self, y: i8, x: i8, v: CellValue) {
         self.vv[y as usize].set(x, v);
+        if v.is_solid() {
+            self.bits[y as usize] |= 1 << x;
+        } else {
+            self.bits[y as usize] &= !(1 << x);
+        }
+    }
+
+    /// Occupancy of row `y`, bit x set for solid cells.
+    #[inline(always)]
+    pub fn row_bits(&self, y: i8) -> u16 {
+        self.bits[y as usize]
+    }
+
+    /// Bits of a complete row.
+    #[inline(always)]
+    pub const fn full_row_bits() -> u16 {
+        ((1u32 << C) - 1) as u16
+    }
+
+    /// Lowest full row, if any.
+    pub fn first_full_row(&self) -> Option<i8> {
+        self.bits
+            .iter()
+            .position(|row| *row == Self::full_row_bits())
+            .map(|y| y as i8)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|row| *row == 0)
+    }
+
+    /// Rows of the piece at its position, as (board row, row bits);
+    /// `None` when a cell is outside the board. Rows without cells have
+    /// no bits set.
+    #[inline(always)]
+    fn placed_rows(info: &CurrentPcsInfo) -> Option<[(usize, u16); 4]> {
+        let mask = piece_mask(info.rot_system, info.tet, info.rs);
+        let (y, x) = info.pos;
+        let mut rows = [(0, 0); 4];
+        for (j, _) in mask.filled_rows() {
+            let cy = y as i32 + j as i32;
+            if cy < 0 || cy >= R as i32 {
+                return None;
+            }
+            rows[j] = (cy as usize, mask.row_at(j, x, C)?);
+        }
+        Some(rows)
+    }
+
+    /// Whether the piece fits on the board, ignoring ghost cells.
+    #[inline(always)]
+    pub fn piece_fits(&self, info: &CurrentPcsInfo) -> bool {
+        match Self::placed_rows(info) {
+            Some(rows) => rows.iter().all(|(y, bits)| self.bits[*y] & bits == 0),
+            None => false,
+        }
+    }
+
+    /// How many rows the piece can fall before it lands.
+    pub fn drop_distance(&self, info: &CurrentPcsInfo) -> i8 {
+        let mut moved = *info;
+        let mut distance = 0;
+        loop {
+            moved.pos.0 -= 1;
+            if !self.piece_fits(&moved) {
+                return distance;
+            }
+            distance += 1;
+        }
+    }
+
+    /// Writes `value` on the cells of the piece, after checking they are
+    /// on the board and, with `check_taken`, not solid.
+    fn set_piece_cells(
+        &mut self,
+        info: &CurrentPcsInfo,
+        value: CellValue,
+        check_taken: bool,
+    ) -> anyhow::Result<()> {
+        let (y, x) = info.pos;
+        let Some(rows) = Self::placed_rows(info) else {
+            anyhow::bail!(
+                "given position out of game bounds (got (x={x} y={y}), max (x={C} y={R})"
+            );
+        };
+        if check_taken && !self.piece_fits(info) {
+            anyhow::bail!("cell position already taken");
+        }
+        for (cy, bits) in rows {
+            for cx in 0..C {
+                if bits & (1 << cx) != 0 {
+                    self.set_cell(cy as i8, cx as i8, value);
+                }
+            }
+        }
+        Ok(())
     }
 
     pub fn rows(&self) -> Vec<Vec<CellValue>> {
@@ -189,11 +369,26 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     }
     #[inline(always)]
     pub fn empty() -> Self {
+        const { assert!(C <= MAX_BOARD_WIDTH, "board too wide") };
         Self {
-            vv: [CellValueRow10::empty(); R],
+            vv: [CellValueRow::empty(); R],
+            bits: [0; R],
         }
     }
 
+    /// Spawn position for a piece: centered (rounding left) and with its
+    /// lowest cell on the row just above the middle of the board.
+    pub fn spawn_pos(&self, tet: Tet, rot_system: RotationSystemKind) -> (i8, i8) {
+        let shape = tet.shape(rot_system, RotState::R0);
+        let lowest_row = shape
+            .iter()
+            .position(|row| row.iter().any(|c| *c))
+            .unwrap_or(0);
+        let y = (R / 2) as i8 - 1 - lowest_row as i8;
+        let x = (C as i8 - shape[0].len() as i8) / 2;
+        (y, x)
+    }
+
     #[inline(always)]
     pub fn get_num_rows(&self) -> usize {
         R
@@ -204,114 +399,17 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     }
 
     pub fn spawn_piece(&mut self, info: &CurrentPcsInfo) -> anyhow::Result<()> {
-        let CurrentPcsInfo {
-            pos: (y, x),
-            tet: piece,
-            rs: rot_state,
-            id: _,
-        } = *info;
-
-        // if x < 0 || y < 0 || x >= (C as i8) || y >= (R as i8) {
-        //     );
-        // }
-        // let (x, y) = (x as usize, y as usize);
-        let shape = piece.shape(rot_state);
-        for (j, row) in shape.iter().enumerate() {
-            for (i, cell) in row.iter().enumerate() {
-                if *cell {
-                    let (cx, cy) = (x + i as i8, y + j as i8);
-                    if cx < 0 || cy < 0 || cx >= (C as i8) || cy >= (R as i8) {
-                        anyhow::bail!(
-                    "given position out of game bounds (got (x={x} y={y}), max (x={C} y={R})");
-                    }
-                    match self.get_cell(cy, cx).unwrap() {
-                        CellValue::Empty | CellValue::Ghost => {}
-                        CellValue::Garbage | CellValue::Piece(_) => {
-                            anyhow::bail!("cell position already taken");
-                        }
-                    }
-                }
-            }
-        }
-
-        for (j, row) in shape.iter().enumerate() {
-            for (i, cell) in row.iter().enumerate() {
-                if *cell {
-                    let (cx, cy) = (x + i as i8, y + j as i8);
-                    if cx < 0 || cy < 0 || cx >= (C as i8) || cy >= (R as i8) {
-                        anyhow::bail!(
-                    "given position out of game bounds (got (x={x} y={y}), max (x={C} y={R})");
-                    }
-                    match self.get_cell(cy, cx).unwrap() {
-                        CellValue::Empty | CellValue::Ghost => {
-                            self.set_cell(cy, cx, CellValue::Piece(piece));
-                        }
-                        CellValue::Garbage | CellValue::Piece(_) => {
-                            anyhow::bail!("cell position already taken");
-                        }
-                    }
-                }
-            }
-        }
-        Ok(())
+        self.set_piece_cells(info, CellValue::Piece(info.tet), true)
     }
 
     pub fn spawn_ghost(&mut self, info: &CurrentPcsInfo) -> anyhow::Result<()> {
-        let CurrentPcsInfo {
-            pos: (y, x),
-            tet: piece,
-            rs: rot_state,
-            id: _,
-        } = *info;
-
-        let shape = piece.shape(rot_state);
-
-        for (j, row) in shape.iter().enumerate() {
-            for (i, cell) in row.iter().enumerate() {
-                if *cell {
-                    let (cx, cy) = (x + i as i8, y + j as i8);
-                    if cx < 0 || cy < 0 || cx >= (C as i8) || cy >= (R as i8) {
-                        anyhow::bail!(
-                    "given position out of game bounds (got (x={x} y={y}), max (x={C} y={R})");
-                    }
-                    match self.get_cell(cy, cx).unwrap() {
-                        CellValue::Empty | CellValue::Ghost => {
-                            self.set_cell(cy, cx, CellValue::Ghost);
-                        }
-                        CellValue::Garbage | CellValue::Piece(_) => {
-                            anyhow::bail!("cell position already taken");
-                        }
-                    }
-                }
-            }
-        }
-        Ok(())
+        self.set_piece_cells(info, CellValue::Ghost, true)
     }
 
     pub fn delete_piece(&mut self, info: &CurrentPcsInfo) -> anyhow::Result<()> {
-        let CurrentPcsInfo {
-            pos: (y, x),
-            tet: piece,
-            rs: rot_state,
-            id: _,
-        } = *info;
-
-        let shape = piece.shape(rot_state);
-        for (j, row) in shape.iter().enumerate() {
-            for (i, cell) in row.iter().enumerate() {
-                if *cell {
-                    let (cx, cy) = (x + i as i8, y + j as i8);
-                    if cx < 0 || cy < 0 || cx >= (C as i8) || cy >= (R as i8) {
-                        anyhow::bail!(
-                    "given position out of game bounds (got (x={x} y={y}), max (x={C} y={R})");
-                    }
-                    self.set_cell(cy, cx, CellValue::Empty);
-                }
-            }
-        }
-        Ok(())
+        self.set_piece_cells(info, CellValue::Empty, false)
     }
-    pub fn spawn_nextpcs(&mut self, next_pcs: &[Tet]) {
+    pub fn spawn_nextpcs(&mut self, next_pcs: &[Tet], rot_system: RotationSystemKind) {
         let col: i8 = 0;
         let mut row: i8 = R as i8 - 4;
         for (i, piece) in next_pcs.iter().enumerate() {
@@ -323,6 +421,7 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
                 pos: (row + if (*piece).eq(&Tet::O) { 1 } else { 0 }, col),
                 tet: *piece,
                 rs: RotState::R0,
+                rot_system,
             };
             let r = self.spawn_piece(&info);
             row -= 3;
@@ -333,48 +432,33 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
     }
 
     pub fn get_height(&self) -> i32 {
-        for i in (0..R).rev() {
-            for j in 0..C {
-                let cc = self.get_cell(i as i8, j as i8).unwrap();
-                match cc {
-                    CellValue::Piece(_) => return i as i32,
-                    CellValue::Garbage => return i as i32,
-                    CellValue::Empty => continue,
-                    CellValue::Ghost => continue,
-                };
-            }
-        }
-        0
+        self.bits.iter().rposition(|row| *row != 0).unwrap_or(0) as i32
+    }
+
+    /// Rows that still hold at least one garbage cell.
+    pub fn count_garbage_rows(&self) -> usize {
+        (0..R as i8)
+            .filter(|y| {
+                (0..C as i8).any(|x| self.get_cell(*y, x) == Some(CellValue::Garbage))
+            })
+            .count()
     }
 
     pub fn get_height_for_column(&self, col: i32) -> i32 {
-        for x in (0..self.get_num_rows()).rev() {
-            match self.get_cell(x as i8, col as i8).unwrap() {
-                CellValue::Piece(_) => return x as i32,
-                CellValue::Garbage => return x as i32,
-                CellValue::Empty => continue,
-                CellValue::Ghost => continue,
-            }
-        }
-        0
+        self.bits
+            .iter()
+            .rposition(|row| row & (1 << col) != 0)
+            .unwrap_or(0) as i32
     }
 
     pub fn board_holes(&self) -> i32 {
         let mut holes: i32 = 0;
-
-        for x in (0..self.get_num_cols()).rev() {
-            let height = self.get_height_for_column(x as i32);
-
-            for y in 0..height {
-                match self.get_cell(y as i8, x as i8).unwrap() {
-                    CellValue::Empty | CellValue::Ghost => {
-                        holes += 1;
-                    }
-                    _ => {}
-                };
-            }
+        // cells with a solid cell somewhere above them
+        let mut covered = 0u16;
+        for row in self.bits.iter().rev() {
+            holes += (covered & !row).count_ones() as i32;
+            covered |= row;
         }
-
         holes
     }
     pub fn board_bumpi(&self) -> i32 {
@@ -394,3 +478,81 @@ impl<const R: usize, const C: usize> BoardMatrix<R, C> {
         max_bumpi
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn fill_row<const R: usize, const C: usize>(b: &mut BoardMatrix<R, C>, y: i8) {
+        for x in 0..C as i8 {
+            b.set_cell(y, x, CellValue::Garbage);
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn spawn_pos_matches_guideline_on_default_board() {
+        let b = BoardMatrix::<40, 10>::empty();
+        let srs = RotationSystemKind::Srs;
+        assert_eq!(b.spawn_pos(Tet::T, srs), (18, 3));
+        assert_eq!(b.spawn_pos(Tet::I, srs), (17, 3));
+        assert_eq!(b.spawn_pos(Tet::O, srs), (19, 4));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn narrow_and_wide_boards() {
+        let mut narrow = BoardMatrix::<12, 4>::empty();
+        assert_eq!(narrow.spawn_pos(Tet::I, RotationSystemKind::Srs), (3, 0));
+        fill_row(&mut narrow, 0);
+        narrow.set_cell(1, 2, CellValue::Garbage);
+        narrow.clear_line(0);
+        assert_eq!(narrow.get_cell(0, 2), Some(CellValue::Garbage));
+        assert_eq!(narrow.get_height(), 0);
+
+        let mut wide = BoardMatrix::<60, 12>::empty();
+        assert_eq!(wide.spawn_pos(Tet::T, RotationSystemKind::Srs), (28, 4));
+        fill_row(&mut wide, 59);
+        wide.inject_single_garbage_line([9; 32]);
+        let garbage = wide.rows()[0].clone();
+        assert_eq!(garbage.len(), 12);
+        let holes = garbage.iter().filter(|c| **c == CellValue::Empty).count();
+        assert_eq!(holes, 1);
+        // the top row gets pushed out of the board
+        assert_eq!(wide.get_height(), 0);
+    }
+    #[test]
+    #[wasm_bindgen_test]
+    fn bitboard_follows_cells() {
+        let mut b = BoardMatrix::<40, 10>::empty();
+        fill_row(&mut b, 0);
+        b.set_cell(1, 4, CellValue::Ghost);
+        b.set_cell(2, 3, CellValue::Piece(Tet::T));
+        assert_eq!(b.first_full_row(), Some(0));
+        assert_eq!(b.row_bits(1), 0);
+        assert_eq!(b.row_bits(2), 1 << 3);
+        assert_eq!(b.board_holes(), 1);
+
+        let bytes = bincode::serialize(&b).unwrap();
+        assert_eq!(bytes.len(), 40 * 5);
+        let loaded: BoardMatrix<40, 10> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(loaded, b);
+        let odd = BoardMatrix::<4, 5>::empty();
+        assert_eq!(bincode::serialize(&odd).unwrap().len(), 4 * 3);
+
+        let info = CurrentPcsInfo {
+            pos: (1, 0),
+            tet: Tet::O,
+            rs: RotState::R0,
+            rot_system: RotationSystemKind::Srs,
+            id: 0,
+        };
+        b.set_cell(1, 4, CellValue::Empty);
+        assert_eq!(b.drop_distance(&info), 0);
+        b.clear_line(0);
+        assert!(b.piece_fits(&info));
+        assert_eq!(b.drop_distance(&info), 1);
+        assert!(!b.is_empty());
+    }
+}
@@ -1,17 +1,58 @@
+mod bitboard;
+mod events;
+mod finesse;
+mod fumen;
 mod game_state;
+mod garbage;
+mod keyframes;
+mod legacy;
+mod level;
+mod lock_delay;
 mod matrix;
 mod random;
+mod randomizer;
+mod replay_file;
 mod rot;
+mod rot_system;
+mod rules;
+mod scoring;
+mod setup;
+mod spin;
+mod stats;
 mod tetpcs;
+mod verify;
 
+pub use bitboard::{piece_mask, PieceMask};
+pub use events::{GameEvent, GameEventList};
+pub use finesse::{minimal_keys, FinesseKey, FinesseTracker};
+pub use fumen::{
+    decode_fumen, encode_fumen, FumenPage, FumenPiece, FumenQuiz, FUMEN_HEIGHT,
+    FUMEN_WIDTH,
+};
 pub use game_state::{
-    segments_to_states, CurrentPcsInfo, GameOverReason, GameReplaySegment,
-    GameReplaySlice, GameState, HoldPcsInfo,
+    segments_to_states, CurrentPcsInfo, GameOverReason, GameReplayInit,
+    GameReplaySegment, GameReplaySlice, GameState, HoldPcsInfo,
 };
+pub use garbage::{AttackTable, GarbageQueue, GarbageRules, PendingGarbage};
+pub use keyframes::{KeyframedReplay, REPLAY_KEYFRAME_INTERVAL};
+pub use legacy::{LegacyGameReplaySegment, LegacyGameState};
+pub use level::{GravityCurve, LevelRules};
+pub use lock_delay::{LockDelayRules, LockDelayState};
 pub use matrix::{BoardMatrix, BoardMatrixHold, BoardMatrixNext, CellValue};
 pub use random::{get_random_seed, GameSeed};
+pub use randomizer::RandomizerKind;
 pub use rot::RotState;
+pub use rot_system::{RotationSystem, RotationSystemKind};
+pub use replay_file::{
+    GameReplayFile, ReplayFileAction, REPLAY_FILE_MAGIC, REPLAY_FORMAT_VERSION,
+};
+pub use rules::{GameRules, RulesVersion};
+pub use scoring::{LineClear, ScoringSystem, ScoringSystemKind};
+pub use setup::{GameSetup, SETUP_QUEUE_LEN};
+pub use spin::SpinKind;
+pub use stats::GameStats;
 pub use tetpcs::{Tet, TetAction};
+pub use verify::verify_next_state;
 
 #[cfg(test)]
 pub mod tests {
@@ -27,6 +68,8 @@ pub mod tests {
     pub fn random_have_pinned_results() {
         let seed = [0; 32];
         let mut state = GameState::new(&seed, 0);
+        // pinned values below are for the default 7-bag
+        assert_eq!(state.rules.randomizer, RandomizerKind::Bag7);
 
         // let expected_seed = [0;32];
         // assert_eq!(expected_seed, state.seed);
@@ -44,6 +87,127 @@ pub mod tests {
         assert_eq!(expected_seed, state.seed);
     }
 
+    #[test]
+    #[wasm_bindgen_test]
+    pub fn rotate_180_works_for_all_pieces() {
+        for tet in Tet::all() {
+            let mut state = GameState::new(&[0; 32], 0);
+            state.set_next_pcs(vec![tet; 14]);
+            state.apply_action_if_works(TetAction::HardDrop, 1).unwrap();
+            let pcs = state.current_pcs.unwrap();
+            assert_eq!(pcs.tet, tet);
+
+            state
+                .apply_action_if_works(TetAction::Rotate180, 2)
+                .unwrap();
+            assert_eq!(state.current_pcs.unwrap().rs, RotState::R2);
+            state
+                .apply_action_if_works(TetAction::Rotate180, 3)
+                .unwrap();
+            assert_eq!(state.current_pcs.unwrap().rs, RotState::R0);
+            assert_eq!(state.current_pcs.unwrap().pos, pcs.pos);
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    pub fn shift_to_wall_and_drop_to_floor() {
+        let mut state = GameState::new(&[0; 32], 0);
+        state.set_next_pcs(vec![Tet::O; 14]);
+        state.apply_action_if_works(TetAction::HardDrop, 1).unwrap();
+        let mut segments = vec![state.last_segment];
+
+        let mut one_by_one = state;
+        while one_by_one.apply_action_if_works(TetAction::MoveLeft, 2).is_ok() {}
+        state.apply_action_if_works(TetAction::MoveLeftToWall, 2).unwrap();
+        segments.push(state.last_segment);
+        assert_eq!(state.current_pcs, one_by_one.current_pcs);
+        assert!(state.try_action(TetAction::MoveLeftToWall, 3).is_err());
+
+        while one_by_one.apply_action_if_works(TetAction::UserSoftDrop, 4).is_ok() {}
+        state.apply_action_if_works(TetAction::SoftDropToFloor, 4).unwrap();
+        segments.push(state.last_segment);
+        assert_eq!(state.current_pcs, one_by_one.current_pcs);
+        assert_eq!(state.score, one_by_one.score);
+
+        // not locked, the piece still moves
+        while one_by_one.apply_action_if_works(TetAction::MoveRight, 5).is_ok() {}
+        state.apply_action_if_works(TetAction::MoveRightToWall, 5).unwrap();
+        segments.push(state.last_segment);
+        assert_eq!(state.current_pcs, one_by_one.current_pcs);
+
+        let segments: Vec<_> = segments[1..].to_vec();
+        let mut replayed = GameState::new(&[0; 32], 0);
+        replayed.set_next_pcs(vec![Tet::O; 14]);
+        replayed.apply_action_if_works(TetAction::HardDrop, 1).unwrap();
+        for segment in &segments {
+            replayed.accept_segment(segment).unwrap();
+        }
+        assert_eq!(replayed.main_board, state.main_board);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    pub fn replay_keeps_rotation_system() {
+        for rotation_system in RotationSystemKind::all() {
+            let rules = GameRules {
+                rotation_system,
+                ..Default::default()
+            };
+            let mut state = GameState::new_with_rules(&[3; 32], 0, rules);
+            let mut segments = vec![state.last_segment];
+            let actions = [
+                TetAction::RotateLeft,
+                TetAction::MoveLeft,
+                TetAction::RotateRight,
+                TetAction::HardDrop,
+                TetAction::Rotate180,
+                TetAction::HardDrop,
+            ];
+            for (i, action) in actions.into_iter().enumerate() {
+                if let Ok(new_state) = state.try_action(action, i as i64 + 1) {
+                    state = new_state;
+                    segments.push(state.last_segment);
+                }
+            }
+            let replayed = segments_to_states(&segments);
+            let last = replayed.last().unwrap();
+            assert_eq!(last.rules, rules);
+            assert_eq!(last.main_board, state.main_board);
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    pub fn replay_keeps_randomizer() {
+        for randomizer in RandomizerKind::all() {
+            let rules = GameRules {
+                randomizer,
+                ..Default::default()
+            };
+            let mut state = GameState::new_with_rules(&[5; 32], 0, rules);
+            let mut segments = vec![state.last_segment];
+            let mut pieces = vec![];
+            for i in 0..60 {
+                let action = [TetAction::MoveLeft, TetAction::MoveRight][i % 2];
+                for action in [action, TetAction::HardDrop] {
+                    if let Ok(new_state) = state.try_action(action, i as i64) {
+                        state = new_state;
+                        segments.push(state.last_segment);
+                        pieces.push(state.current_pcs.map(|p| p.tet));
+                    }
+                }
+                assert_eq!(state.get_next_pcs().len(), 5);
+            }
+            let replayed = segments_to_states(&segments);
+            let replayed_pieces: Vec<_> = replayed[1..]
+                .iter()
+                .map(|s| s.current_pcs.map(|p| p.tet))
+                .collect();
+            assert_eq!(pieces, replayed_pieces, "{randomizer:?}");
+        }
+    }
+
     #[test]
     #[wasm_bindgen_test]
     #[allow(clippy::unnecessary_unwrap)]
@@ -71,6 +235,18 @@ pub mod tests {
         }
     }
 
+    /// `state_data` columns hold the base64 of the postcard state in a
+    /// VARCHAR(2048).
+    #[test]
+    #[wasm_bindgen_test]
+    fn saved_state_fits_the_database_column() {
+        use base64::Engine;
+        let state = GameState::new(&[1; 32], get_timestamp_now_ms());
+        let bytes = postcard::to_allocvec(&state).unwrap();
+        let encoded = base64::prelude::BASE64_URL_SAFE.encode(bytes);
+        assert!(encoded.len() <= 2048, "{} base64 chars", encoded.len());
+    }
+
     #[test]
     #[wasm_bindgen_test]
     fn passive_game_tracks_active_one() {
@@ -7,7 +7,7 @@ use rand::{
 use rand_chacha::ChaCha20Rng;
 pub type GameSeed = <ChaCha20Rng as SeedableRng>::Seed;
 
-fn get_rng(seed: &GameSeed) -> ChaCha20Rng {
+pub fn get_rng(seed: &GameSeed) -> ChaCha20Rng {
     // let mut seed = seed.clone();
     // rand:    :thread_rng().fill(&mut seed);
     ChaCha20Rng::from_seed(*seed)
@@ -92,6 +92,8 @@ pub mod tests {
         let expected_str2: Vec<u8> = vec![2, 0, 0, 0];
         assert_eq!(encoded_str1, expected_str1);
         assert_eq!(encoded_str2, expected_str2);
+        let encoded_str180 = bincode::serialize(&TetAction::Rotate180).unwrap();
+        assert_eq!(encoded_str180, vec![9, 0, 0, 0]);
 
         let evt1 = GameReplayEvent {
             action: TetAction::UserSoftDrop,
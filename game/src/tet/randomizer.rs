@@ -0,0 +1,175 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    random::{get_rng, shuffle_tets, GameSeed},
+    tetpcs::Tet,
+};
+
+/// Piece generators. Every one of them only draws from the `GameSeed` and
+/// the pieces already generated, so the queue replays exactly.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Default,
+)]
+pub enum RandomizerKind {
+    #[default]
+    Bag7,
+    Bag14,
+    PureRandom,
+    TgmHistory4,
+    ClassicNes,
+}
+
+impl RandomizerKind {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Bag7,
+            Self::Bag14,
+            Self::PureRandom,
+            Self::TgmHistory4,
+            Self::ClassicNes,
+        ]
+    }
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Bag7 => "7-bag",
+            Self::Bag14 => "14-bag",
+            Self::PureRandom => "Random",
+            Self::TgmHistory4 => "TGM",
+            Self::ClassicNes => "Classic",
+        }
+    }
+
+    /// Generate the next chunk of pieces. `history` is every piece still in
+    /// the queue, oldest first.
+    pub fn next_chunk(
+        &self,
+        seed: &GameSeed,
+        event_time: i64,
+        history: &[Tet],
+    ) -> (Vec<Tet>, GameSeed) {
+        let mut rng = get_rng(&mix_event_time(seed, event_time));
+        let all = Tet::all();
+        let chunk = match self {
+            Self::Bag7 => return shuffle_tets(seed, event_time),
+            Self::Bag14 => {
+                let mut v: Vec<Tet> = all.iter().chain(all.iter()).cloned().collect();
+                v.shuffle(&mut rng);
+                v
+            }
+            Self::PureRandom => (0..7)
+                .map(|_| all[rng.random_range(0..all.len())])
+                .collect(),
+            Self::TgmHistory4 => {
+                let mut history: Vec<Tet> = history.to_vec();
+                let mut v = vec![];
+                for _ in 0..7 {
+                    let next = if history.is_empty() {
+                        // first piece is never S, Z or O
+                        [Tet::I, Tet::J, Tet::L, Tet::T][rng.random_range(0..4)]
+                    } else {
+                        let recent = tgm_recent(&history);
+                        let mut next = all[rng.random_range(0..all.len())];
+                        for _ in 1..4 {
+                            if !recent.contains(&next) {
+                                break;
+                            }
+                            next = all[rng.random_range(0..all.len())];
+                        }
+                        next
+                    };
+                    history.push(next);
+                    v.push(next);
+                }
+                v
+            }
+            Self::ClassicNes => {
+                let mut prev = history.last().cloned();
+                let mut v = vec![];
+                for _ in 0..7 {
+                    // roll 8 sides, the 8th and a repeat of the last piece
+                    // get one reroll on 7 sides
+                    let roll = rng.random_range(0..8);
+                    let next = if roll == 7 || Some(all[roll]) == prev {
+                        all[rng.random_range(0..all.len())]
+                    } else {
+                        all[roll]
+                    };
+                    prev = Some(next);
+                    v.push(next);
+                }
+                v
+            }
+        };
+        let new_seed = rng.random();
+        (chunk, new_seed)
+    }
+}
+
+/// Last 4 pieces, padded with Z like the original TGM history.
+fn tgm_recent(history: &[Tet]) -> Vec<Tet> {
+    let mut recent: Vec<Tet> = history.iter().rev().take(4).cloned().collect();
+    while recent.len() < 4 {
+        recent.push(Tet::Z);
+    }
+    recent
+}
+
+fn mix_event_time(seed: &GameSeed, event_time: i64) -> GameSeed {
+    let event_time = event_time.to_le_bytes();
+    let mut seed = *seed;
+    for i in 0..8 {
+        seed[i] ^= event_time[i];
+    }
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn randomizers_are_deterministic() {
+        for kind in RandomizerKind::all() {
+            let (a, seed_a) = kind.next_chunk(&[7; 32], 123, &[Tet::S]);
+            let (b, seed_b) = kind.next_chunk(&[7; 32], 123, &[Tet::S]);
+            assert_eq!(a, b, "{kind:?}");
+            assert_eq!(seed_a, seed_b, "{kind:?}");
+            assert!(!a.is_empty());
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn bags_contain_every_piece() {
+        let (mut bag, _) = RandomizerKind::Bag7.next_chunk(&[1; 32], 0, &[]);
+        bag.sort();
+        assert_eq!(bag, Tet::all());
+
+        let (bag, _) = RandomizerKind::Bag14.next_chunk(&[1; 32], 0, &[]);
+        for tet in Tet::all() {
+            assert_eq!(bag.iter().filter(|t| **t == tet).count(), 2);
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn tgm_first_piece_is_not_s_z_o() {
+        for i in 0..=255 {
+            let (v, _) = RandomizerKind::TgmHistory4.next_chunk(&[i; 32], 0, &[]);
+            assert!(![Tet::S, Tet::Z, Tet::O].contains(&v[0]));
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use anyhow::Context;
+
+use super::{
+    game_state::{
+        segments_to_states, GameOverReason, GameReplayInit, GameReplaySegment,
+        GameState,
+    },
+    rules::RulesVersion,
+    tetpcs::TetAction,
+};
+
+pub const REPLAY_FILE_MAGIC: [u8; 4] = *b"SPRP";
+pub const REPLAY_FORMAT_VERSION: u16 = 1;
+
+/// One player input of a replay file. Seeds, slice indices and applied
+/// garbage are not stored, they come out of the simulation again.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct ReplayFileAction {
+    pub action: TetAction,
+    pub timestamp: i64,
+    /// total garbage received from opponents when the action was played
+    pub garbage_recv: u16,
+}
+
+/// Self-describing `.replay` file:
+///
+/// - magic `SPRP`, format version and the rules version of the init
+///   segment (`u16` little endian)
+/// - `GameReplayInit` as bincode, prefixed with its `u32` length
+/// - action count, then per action the `TetAction` byte and the zigzag
+///   varint deltas of the timestamp and of the garbage received
+/// - a flag byte and the `GameOverReason` as bincode when the game ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameReplayFile {
+    pub init: GameReplayInit,
+    pub actions: Vec<ReplayFileAction>,
+    pub game_over: Option<GameOverReason>,
+}
+
+impl GameReplayFile {
+    pub fn from_segments(segments: &[GameReplaySegment]) -> anyhow::Result<Self> {
+        let Some(GameReplaySegment::Init(init)) = segments.first() else {
+            anyhow::bail!("replay does not start with an init segment");
+        };
+        let mut actions = vec![];
+        let mut game_over = None;
+        for segment in &segments[1..] {
+            match segment {
+                GameReplaySegment::Init(_) => anyhow::bail!("got two init segments"),
+                GameReplaySegment::Update(slice) => {
+                    if slice.idx as usize != actions.len() {
+                        anyhow::bail!(
+                            "slice mismatch: got slice {} expected slice {}",
+                            slice.idx,
+                            actions.len()
+                        );
+                    }
+                    actions.push(ReplayFileAction {
+                        action: slice.event.action,
+                        timestamp: slice.event_timestamp,
+                        garbage_recv: slice.new_garbage_recv,
+                    });
+                }
+                GameReplaySegment::GameOver(reason) => game_over = Some(*reason),
+            }
+        }
+        Ok(Self {
+            init: *init,
+            actions,
+            game_over,
+        })
+    }
+
+    /// Plays the actions again from the init segment, giving back the
+    /// segments the game produced.
+    pub fn to_segments(&self) -> anyhow::Result<Vec<GameReplaySegment>> {
+        let mut segments = vec![GameReplaySegment::Init(self.init)];
+        let mut state = GameState::from_init(&self.init);
+        for (i, action) in self.actions.iter().enumerate() {
+            state.garbage_recv = action.garbage_recv;
+            state = state
+                .try_action(action.action, action.timestamp)
+                .with_context(|| format!("replay action {i} failed: {action:?}"))?;
+            segments.push(state.last_segment);
+        }
+        if let Some(reason) = self.game_over {
+            segments.push(GameReplaySegment::GameOver(reason));
+        }
+        Ok(segments)
+    }
+
+    /// All the states of the game, checked with `segments_to_states`.
+    pub fn validate(&self) -> anyhow::Result<Vec<GameState>> {
+        let segments = self.to_segments()?;
+        let states = segments_to_states(&segments);
+        if states.len() != segments.len() {
+            anyhow::bail!("replay segments do not simulate");
+        }
+        Ok(states)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        out.extend_from_slice(&REPLAY_FILE_MAGIC);
+        out.extend_from_slice(&REPLAY_FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.init.rules.version.number().to_le_bytes());
+        let init = bincode::serialize(&self.init).unwrap();
+        out.extend_from_slice(&(init.len() as u32).to_le_bytes());
+        out.extend_from_slice(&init);
+
+        write_varint(&mut out, self.actions.len() as u64);
+        let mut last_time = self.init.start_time;
+        let mut last_garbage = 0;
+        for action in &self.actions {
+            out.push(action_to_byte(action.action));
+            write_zigzag(&mut out, action.timestamp - last_time);
+            write_zigzag(&mut out, action.garbage_recv as i64 - last_garbage as i64);
+            last_time = action.timestamp;
+            last_garbage = action.garbage_recv;
+        }
+
+        match self.game_over {
+            Some(reason) => {
+                out.push(1);
+                out.extend_from_slice(&bincode::serialize(&reason).unwrap());
+            }
+            None => out.push(0),
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(4)? != REPLAY_FILE_MAGIC {
+            anyhow::bail!("not a replay file");
+        }
+        let format_version = r.read_u16()?;
+        if format_version != REPLAY_FORMAT_VERSION {
+            anyhow::bail!("unsupported replay format version {format_version}");
+        }
+        let rules_version = RulesVersion::from_number(r.read_u16()?)?;
+        let init_len = r.read_u32()? as usize;
+        let init: GameReplayInit = bincode::deserialize(r.take(init_len)?)
+            .context("bad replay init segment")?;
+        if init.rules.version != rules_version {
+            anyhow::bail!(
+                "replay header and init segment disagree on the rules version"
+            );
+        }
+
+        let count = r.read_varint()?;
+        let mut actions = vec![];
+        let mut last_time = init.start_time;
+        let mut last_garbage = 0i64;
+        for _ in 0..count {
+            let action = action_from_byte(r.take(1)?[0])?;
+            last_time += r.read_zigzag()?;
+            last_garbage += r.read_zigzag()?;
+            let garbage_recv =
+                u16::try_from(last_garbage).context("bad garbage count in replay")?;
+            actions.push(ReplayFileAction {
+                action,
+                timestamp: last_time,
+                garbage_recv,
+            });
+        }
+
+        let game_over = match r.take(1)?[0] {
+            0 => None,
+            1 => Some(
+                bincode::deserialize(&r.bytes[r.pos..])
+                    .context("bad game over reason")?,
+            ),
+            x => anyhow::bail!("bad game over flag {x}"),
+        };
+        Ok(Self {
+            init,
+            actions,
+            game_over,
+        })
+    }
+}
+
+/// `TetAction` is encoded the way the replay seeds hash it: its bincode
+/// variant index, which fits in one byte.
+fn action_to_byte(action: TetAction) -> u8 {
+    let bytes = bincode::serialize(&action).unwrap();
+    bytes[0]
+}
+
+fn action_from_byte(byte: u8) -> anyhow::Result<TetAction> {
+    bincode::deserialize(&(byte as u32).to_le_bytes())
+        .with_context(|| format!("bad action {byte} in replay"))
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn write_zigzag(out: &mut Vec<u8>, v: i64) {
+    write_varint(out, ((v << 1) ^ (v >> 63)) as u64);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).context("replay file too short")?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .context("replay file too short")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+    fn read_u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+    fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.take(1)?[0];
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        anyhow::bail!("varint too long in replay file")
+    }
+    fn read_zigzag(&mut self) -> anyhow::Result<i64> {
+        let v = self.read_varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn play_game() -> Vec<GameReplaySegment> {
+        let mut state = GameState::new(&[5; 32], 1_000);
+        let mut segments = vec![state.last_segment];
+        for i in 0..40 {
+            let action = [TetAction::MoveLeft, TetAction::RotateRight][i % 2];
+            for (j, action) in [action, TetAction::UserSoftDrop, TetAction::HardDrop]
+                .into_iter()
+                .enumerate()
+            {
+                if i == 20 {
+                    state.garbage_recv += 2;
+                }
+                let t = 1_000 + i as i64 * 300 + j as i64 * 40;
+                if let Ok(s) = state.try_action(action, t) {
+                    state = s;
+                    segments.push(state.last_segment);
+                }
+            }
+            if state.game_over() {
+                break;
+            }
+        }
+        segments
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn replay_file_round_trip() {
+        let segments = play_game();
+        let file = GameReplayFile::from_segments(&segments).unwrap();
+        let bytes = file.to_bytes();
+        // a few bytes per action, the init segment is most of the file
+        assert!(bytes.len() < 200 + file.actions.len() * 4);
+
+        let loaded = GameReplayFile::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, file);
+        assert_eq!(loaded.to_segments().unwrap(), segments);
+        let states = loaded.validate().unwrap();
+        assert_eq!(states, segments_to_states(&segments));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn bad_replay_files() {
+        let file = GameReplayFile::from_segments(&play_game()).unwrap();
+        let bytes = file.to_bytes();
+        assert!(GameReplayFile::from_bytes(&bytes[..bytes.len() - 3]).is_err());
+        assert!(GameReplayFile::from_bytes(b"nope").is_err());
+
+        let mut newer = bytes.clone();
+        newer[4] = 99;
+        assert!(GameReplayFile::from_bytes(&newer).is_err());
+
+        // written by a newer build of the game
+        let mut future_rules = bytes.clone();
+        future_rules[6..8]
+            .copy_from_slice(&(RulesVersion::LATEST.number() + 1).to_le_bytes());
+        assert!(GameReplayFile::from_bytes(&future_rules).is_err());
+    }
+}
@@ -30,7 +30,20 @@ pub fn srs_offsets(before: RotState, after: RotState, tet: Tet) -> Vec<(i8, i8)>
             (RotState::R0, RotState::R3) => {
                 vec![(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]
             }
-            _ => panic!("180 or 0 rot is bad"),
+            // 180 kicks
+            (RotState::R0, RotState::R2) => {
+                vec![(0, 0), (-1, 0), (-2, 0), (1, 0), (2, 0), (0, 1)]
+            }
+            (RotState::R2, RotState::R0) => {
+                vec![(0, 0), (1, 0), (2, 0), (-1, 0), (-2, 0), (0, -1)]
+            }
+            (RotState::R1, RotState::R3) => {
+                vec![(0, 0), (0, 1), (0, 2), (0, -1), (0, -2), (-1, 0)]
+            }
+            (RotState::R3, RotState::R1) => {
+                vec![(0, 0), (0, 1), (0, 2), (0, -1), (0, -2), (1, 0)]
+            }
+            _ => panic!("0 rot is bad"),
         },
         _ => match (before, after) {
             (RotState::R0, RotState::R1) => {
@@ -57,7 +70,20 @@ pub fn srs_offsets(before: RotState, after: RotState, tet: Tet) -> Vec<(i8, i8)>
             (RotState::R0, RotState::R3) => {
                 vec![(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]
             }
-            _ => panic!("180 or 0 rot is bad"),
+            // 180 kicks (SRS+, same as TETR.IO)
+            (RotState::R0, RotState::R2) => {
+                vec![(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)]
+            }
+            (RotState::R2, RotState::R0) => {
+                vec![(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)]
+            }
+            (RotState::R1, RotState::R3) => {
+                vec![(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)]
+            }
+            (RotState::R3, RotState::R1) => {
+                vec![(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)]
+            }
+            _ => panic!("0 rot is bad"),
         },
     }
 }
@@ -65,6 +91,7 @@ pub fn srs_offsets(before: RotState, after: RotState, tet: Tet) -> Vec<(i8, i8)>
 pub enum RotDirection {
     Left,
     Right,
+    Half,
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -88,6 +115,11 @@ impl RotState {
             (&Self::R1, RotDirection::Right) => Self::R2,
             (&Self::R2, RotDirection::Right) => Self::R3,
             (&Self::R3, RotDirection::Right) => Self::R0,
+
+            (&Self::R0, RotDirection::Half) => Self::R2,
+            (&Self::R1, RotDirection::Half) => Self::R3,
+            (&Self::R2, RotDirection::Half) => Self::R0,
+            (&Self::R3, RotDirection::Half) => Self::R1,
         }
     }
 }
@@ -122,6 +154,10 @@ pub fn rotate_shape(shape: Shape, rot: RotDirection) -> Shape {
                 new_shape.push(new_row);
             }
         }
+        RotDirection::Half => {
+            let half = rotate_shape(shape, RotDirection::Right);
+            return rotate_shape(half, RotDirection::Right);
+        }
     }
 
     new_shape
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    rot::{rotate_shape, srs_offsets, RotDirection, RotState, Shape},
+    tetpcs::Tet,
+};
+
+/// Shapes and wall kicks for one rotation system.
+pub trait RotationSystem: Send + Sync {
+    /// Piece shape in spawn orientation, rows from bottom to top.
+    fn spawn_shape(&self, tet: Tet) -> Shape;
+
+    /// Piece shape for any rotation state. Defaults to rotating the spawn
+    /// shape clockwise around the center of its bounding box.
+    fn shape(&self, tet: Tet, rot_state: RotState) -> Shape {
+        let sh = self.spawn_shape(tet);
+        match rot_state {
+            RotState::R0 => sh,
+            RotState::R1 => rotate_shape(sh, RotDirection::Right),
+            RotState::R2 => rotate_shape(sh, RotDirection::Half),
+            RotState::R3 => rotate_shape(sh, RotDirection::Left),
+        }
+    }
+
+    /// Offsets (x, y) to try in order when rotating; first one that fits wins.
+    fn kicks(&self, before: RotState, after: RotState, tet: Tet) -> Vec<(i8, i8)>;
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Default,
+)]
+pub enum RotationSystemKind {
+    #[default]
+    Srs,
+    Ars,
+    ClassicNes,
+}
+
+impl RotationSystemKind {
+    pub fn get(&self) -> &'static dyn RotationSystem {
+        match self {
+            Self::Srs => &SuperRotationSystem,
+            Self::Ars => &ArikaRotationSystem,
+            Self::ClassicNes => &ClassicNesRotationSystem,
+        }
+    }
+    pub fn all() -> Vec<Self> {
+        vec![Self::Srs, Self::Ars, Self::ClassicNes]
+    }
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Srs => "SRS",
+            Self::Ars => "ARS",
+            Self::ClassicNes => "Classic",
+        }
+    }
+}
+
+/// Build a shape from text rows written top to bottom, `#` is a full cell.
+fn shape_from_rows(rows: &[&str]) -> Shape {
+    rows.iter()
+        .rev()
+        .map(|row| row.chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+/// Guideline rotation system, with SRS+ 180 kicks.
+pub struct SuperRotationSystem;
+
+impl RotationSystem for SuperRotationSystem {
+    fn spawn_shape(&self, tet: Tet) -> Shape {
+        match tet {
+            Tet::I => shape_from_rows(&["....", "####", "....", "...."]),
+            Tet::L => shape_from_rows(&["..#", "###", "..."]),
+            Tet::J => shape_from_rows(&["#..", "###", "..."]),
+            Tet::T => shape_from_rows(&[".#.", "###", "..."]),
+            Tet::S => shape_from_rows(&[".##", "##.", "..."]),
+            Tet::Z => shape_from_rows(&["##.", ".##", "..."]),
+            Tet::O => shape_from_rows(&["##", "##"]),
+        }
+    }
+
+    fn kicks(&self, before: RotState, after: RotState, tet: Tet) -> Vec<(i8, i8)> {
+        srs_offsets(before, after, tet)
+    }
+}
+
+/// Arika rotation system (TGM): pieces spawn flat side up and rest on the
+/// bottom of their box; simple one-cell wall kicks, none for the I piece.
+pub struct ArikaRotationSystem;
+
+impl RotationSystem for ArikaRotationSystem {
+    fn spawn_shape(&self, tet: Tet) -> Shape {
+        self.shape(tet, RotState::R0)
+    }
+
+    fn shape(&self, tet: Tet, rot_state: RotState) -> Shape {
+        use RotState::*;
+        let rows: &[&str] = match (tet, rot_state) {
+            (Tet::I, R0 | R2) => &["....", "####", "....", "...."],
+            (Tet::I, R1 | R3) => &["..#.", "..#.", "..#.", "..#."],
+            (Tet::T, R0) => &["...", "###", ".#."],
+            (Tet::T, R1) => &[".#.", "##.", ".#."],
+            (Tet::T, R2) => &["...", ".#.", "###"],
+            (Tet::T, R3) => &[".#.", ".##", ".#."],
+            (Tet::L, R0) => &["...", "###", "#.."],
+            (Tet::L, R1) => &["##.", ".#.", ".#."],
+            (Tet::L, R2) => &["...", "..#", "###"],
+            (Tet::L, R3) => &[".#.", ".#.", ".##"],
+            (Tet::J, R0) => &["...", "###", "..#"],
+            (Tet::J, R1) => &[".#.", ".#.", "##."],
+            (Tet::J, R2) => &["...", "#..", "###"],
+            (Tet::J, R3) => &[".##", ".#.", ".#."],
+            (Tet::S, R0 | R2) => &["...", ".##", "##."],
+            (Tet::S, R1 | R3) => &["#..", "##.", ".#."],
+            (Tet::Z, R0 | R2) => &["...", "##.", ".##"],
+            (Tet::Z, R1 | R3) => &["..#", ".##", ".#."],
+            (Tet::O, _) => &["##", "##"],
+        };
+        shape_from_rows(rows)
+    }
+
+    fn kicks(&self, _before: RotState, _after: RotState, tet: Tet) -> Vec<(i8, i8)> {
+        match tet {
+            Tet::I => vec![(0, 0)],
+            _ => vec![(0, 0), (1, 0), (-1, 0)],
+        }
+    }
+}
+
+/// Nintendo rotation system: right-handed, S/Z/I only have two states,
+/// no wall kicks at all.
+pub struct ClassicNesRotationSystem;
+
+impl RotationSystem for ClassicNesRotationSystem {
+    fn spawn_shape(&self, tet: Tet) -> Shape {
+        self.shape(tet, RotState::R0)
+    }
+
+    fn shape(&self, tet: Tet, rot_state: RotState) -> Shape {
+        use RotState::*;
+        let rows: &[&str] = match (tet, rot_state) {
+            (Tet::I, R0 | R2) => &["....", "....", "####", "...."],
+            (Tet::I, R1 | R3) => &["..#.", "..#.", "..#.", "..#."],
+            (Tet::T, R0) => &["...", "###", ".#."],
+            (Tet::T, R1) => &[".#.", "##.", ".#."],
+            (Tet::T, R2) => &[".#.", "###", "..."],
+            (Tet::T, R3) => &[".#.", ".##", ".#."],
+            (Tet::L, R0) => &["...", "###", "#.."],
+            (Tet::L, R1) => &["##.", ".#.", ".#."],
+            (Tet::L, R2) => &["..#", "###", "..."],
+            (Tet::L, R3) => &[".#.", ".#.", ".##"],
+            (Tet::J, R0) => &["...", "###", "..#"],
+            (Tet::J, R1) => &[".#.", ".#.", "##."],
+            (Tet::J, R2) => &["#..", "###", "..."],
+            (Tet::J, R3) => &[".##", ".#.", ".#."],
+            (Tet::S, R0 | R2) => &["...", ".##", "##."],
+            (Tet::S, R1 | R3) => &[".#.", ".##", "..#"],
+            (Tet::Z, R0 | R2) => &["...", "##.", ".##"],
+            (Tet::Z, R1 | R3) => &["..#", ".##", ".#."],
+            (Tet::O, _) => &["##", "##"],
+        };
+        shape_from_rows(rows)
+    }
+
+    fn kicks(&self, _before: RotState, _after: RotState, _tet: Tet) -> Vec<(i8, i8)> {
+        vec![(0, 0)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn all_shapes_have_four_cells() {
+        for system in RotationSystemKind::all() {
+            for tet in Tet::all() {
+                for rs in [RotState::R0, RotState::R1, RotState::R2, RotState::R3] {
+                    let shape = system.get().shape(tet, rs);
+                    let count = shape.iter().flatten().filter(|c| **c).count();
+                    assert_eq!(count, 4, "{system:?} {tet:?} {rs:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn srs_t_points_up_then_right() {
+        let srs = RotationSystemKind::Srs.get();
+        let r0 = srs.shape(Tet::T, RotState::R0);
+        assert_eq!(r0, shape_from_rows(&[".#.", "###", "..."]));
+        let r1 = srs.shape(Tet::T, RotState::R1);
+        assert_eq!(r1, shape_from_rows(&[".#.", ".##", ".#."]));
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use super::spin::SpinKind;
+
+/// Everything a scoring system needs to know about one piece lock.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct LineClear {
+    /// can be 0, for spins that do not clear anything
+    pub lines: u16,
+    pub spin: SpinKind,
+    pub perfect_clear: bool,
+    /// this clear continues a back-to-back chain
+    pub b2b: bool,
+    /// combo counter including this clear, 0 for the first clear
+    pub combo: i8,
+    pub level: u16,
+}
+
+pub trait ScoringSystem: Send + Sync {
+    fn clear_points(&self, clear: &LineClear) -> i32;
+    fn soft_drop_points(&self, rows: i32) -> i32;
+    fn hard_drop_points(&self, rows: i32) -> i32;
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Default,
+)]
+pub enum ScoringSystemKind {
+    #[default]
+    Guideline,
+    Nes,
+    TetrioLike,
+}
+
+impl ScoringSystemKind {
+    pub fn get(&self) -> &'static dyn ScoringSystem {
+        match self {
+            Self::Guideline => &GuidelineScoring,
+            Self::Nes => &NesScoring,
+            Self::TetrioLike => &TetrioLikeScoring,
+        }
+    }
+    pub fn all() -> Vec<Self> {
+        vec![Self::Guideline, Self::Nes, Self::TetrioLike]
+    }
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Guideline => "Guideline",
+            Self::Nes => "NES",
+            Self::TetrioLike => "TETR.IO",
+        }
+    }
+}
+
+/// Base points of the guideline table, before level and b2b multipliers.
+fn guideline_base_points(lines: u16, spin: SpinKind) -> i32 {
+    match (spin, lines) {
+        (SpinKind::None, 0) => 0,
+        (SpinKind::None, 1) => 100,
+        (SpinKind::None, 2) => 300,
+        (SpinKind::None, 3) => 500,
+        (SpinKind::None, _) => 800,
+        (SpinKind::Mini, 0) => 100,
+        (SpinKind::Mini, 1) => 200,
+        (SpinKind::Mini, _) => 400,
+        (SpinKind::Full, 0) => 400,
+        (SpinKind::Full, 1) => 800,
+        (SpinKind::Full, 2) => 1200,
+        (SpinKind::Full, _) => 1600,
+    }
+}
+
+fn b2b_points(points: i32, b2b: bool) -> i32 {
+    if b2b {
+        points * 3 / 2
+    } else {
+        points
+    }
+}
+
+/// Tetris guideline: everything is multiplied by the level.
+pub struct GuidelineScoring;
+
+impl ScoringSystem for GuidelineScoring {
+    fn clear_points(&self, clear: &LineClear) -> i32 {
+        let level = clear.level.max(1) as i32;
+        let mut points =
+            b2b_points(guideline_base_points(clear.lines, clear.spin), clear.b2b);
+        if clear.lines > 0 && clear.combo > 0 {
+            points += 50 * clear.combo as i32;
+        }
+        if clear.perfect_clear {
+            points += match (clear.lines, clear.b2b) {
+                (4, true) => 3200,
+                (1, _) => 800,
+                (2, _) => 1200,
+                (3, _) => 1800,
+                _ => 2000,
+            };
+        }
+        points * level
+    }
+    fn soft_drop_points(&self, rows: i32) -> i32 {
+        rows
+    }
+    fn hard_drop_points(&self, rows: i32) -> i32 {
+        2 * rows
+    }
+}
+
+/// NES: only line clears count, multiplied by level + 1.
+pub struct NesScoring;
+
+impl ScoringSystem for NesScoring {
+    fn clear_points(&self, clear: &LineClear) -> i32 {
+        let base = match clear.lines {
+            0 => 0,
+            1 => 40,
+            2 => 100,
+            3 => 300,
+            _ => 1200,
+        };
+        base * (clear.level as i32 + 1)
+    }
+    fn soft_drop_points(&self, rows: i32) -> i32 {
+        rows
+    }
+    fn hard_drop_points(&self, _rows: i32) -> i32 {
+        0
+    }
+}
+
+/// Guideline table without the level multiplier and a flat perfect clear
+/// bonus, like TETR.IO.
+pub struct TetrioLikeScoring;
+
+impl ScoringSystem for TetrioLikeScoring {
+    fn clear_points(&self, clear: &LineClear) -> i32 {
+        let mut points =
+            b2b_points(guideline_base_points(clear.lines, clear.spin), clear.b2b);
+        if clear.lines > 0 && clear.combo > 0 {
+            points += 50 * clear.combo as i32;
+        }
+        if clear.perfect_clear {
+            points += 3500;
+        }
+        points
+    }
+    fn soft_drop_points(&self, rows: i32) -> i32 {
+        rows
+    }
+    fn hard_drop_points(&self, rows: i32) -> i32 {
+        2 * rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn clear(lines: u16, spin: SpinKind) -> LineClear {
+        LineClear {
+            lines,
+            spin,
+            perfect_clear: false,
+            b2b: false,
+            combo: 0,
+            level: 1,
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn guideline_table() {
+        let g = ScoringSystemKind::Guideline.get();
+        assert_eq!(g.clear_points(&clear(4, SpinKind::None)), 800);
+        assert_eq!(g.clear_points(&clear(2, SpinKind::Full)), 1200);
+        assert_eq!(g.clear_points(&clear(1, SpinKind::Mini)), 200);
+        let b2b_tsd = LineClear {
+            b2b: true,
+            level: 2,
+            ..clear(2, SpinKind::Full)
+        };
+        assert_eq!(g.clear_points(&b2b_tsd), 3600);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn nes_ignores_spins_and_uses_level_plus_one() {
+        let nes = ScoringSystemKind::Nes.get();
+        let tetris = LineClear {
+            level: 0,
+            ..clear(4, SpinKind::None)
+        };
+        assert_eq!(nes.clear_points(&tetris), 1200);
+        let tsd = LineClear {
+            level: 9,
+            ..clear(2, SpinKind::Full)
+        };
+        assert_eq!(nes.clear_points(&tsd), 1000);
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fumen::{FumenPage, FUMEN_HEIGHT, FUMEN_WIDTH},
+    matrix::{BoardMatrix, CellValue},
+    tetpcs::Tet,
+};
+
+/// Longest queue a setup can fix, the current piece included.
+pub const SETUP_QUEUE_LEN: usize = 21;
+/// Rows of the board a setup can fill, as many as a fumen field shows.
+pub const SETUP_HEIGHT: usize = FUMEN_HEIGHT;
+const SETUP_ROW_BYTES: usize = FUMEN_WIDTH.div_ceil(2);
+
+/// Starting position of a setup game: the board, the queue (current piece
+/// first) and the hold piece. Pieces after the queue come from the
+/// randomizer. Stored in `GameReplayInit` so replays start from it too.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSetup {
+    /// bottom rows of the board, two cells per byte, so that the init
+    /// segment holding the setup stays small
+    rows: [[u8; SETUP_ROW_BYTES]; SETUP_HEIGHT],
+    queue: [Option<Tet>; SETUP_QUEUE_LEN],
+    pub hold: Option<Tet>,
+}
+
+impl GameSetup {
+    /// Pieces past `SETUP_QUEUE_LEN` and cells above `SETUP_HEIGHT` are
+    /// dropped.
+    pub fn new(board: BoardMatrix, queue: &[Tet], hold: Option<Tet>) -> Self {
+        let mut fixed = [None; SETUP_QUEUE_LEN];
+        for (slot, tet) in fixed.iter_mut().zip(queue) {
+            *slot = Some(*tet);
+        }
+        let mut rows = [[0; SETUP_ROW_BYTES]; SETUP_HEIGHT];
+        for (y, row) in rows.iter_mut().enumerate() {
+            for x in 0..FUMEN_WIDTH {
+                let cell = board.get_cell(y as i8, x as i8).unwrap_or(CellValue::Empty);
+                row[x / 2] |= cell.into_bits() << if x % 2 == 0 { 4 } else { 0 };
+            }
+        }
+        Self {
+            rows,
+            queue: fixed,
+            hold,
+        }
+    }
+
+    pub fn board(&self) -> BoardMatrix {
+        let mut board = BoardMatrix::empty();
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in 0..FUMEN_WIDTH {
+                let bits = if x % 2 == 0 {
+                    row[x / 2] >> 4
+                } else {
+                    row[x / 2] & 0xf
+                };
+                board.set_cell(y as i8, x as i8, CellValue::from_bits(bits));
+            }
+        }
+        board
+    }
+
+    pub fn queue(&self) -> Vec<Tet> {
+        self.queue.iter().map_while(|t| *t).collect()
+    }
+
+    /// Field, hold and queue of a fumen page; the queue comes from the
+    /// quiz comment, or is the piece of the page when there is none.
+    pub fn from_fumen_page(page: &FumenPage) -> Self {
+        let quiz = page.quiz().unwrap_or_default();
+        let queue: Vec<_> = quiz
+            .current
+            .or(page.piece.map(|p| p.tet))
+            .into_iter()
+            .chain(quiz.next)
+            .collect();
+        Self::new(page.board(), &queue, quiz.hold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{segments_to_states, GameState, TetAction};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn replay_starts_from_setup() {
+        let mut board = BoardMatrix::empty();
+        for x in 0..9 {
+            board.set_cell(0, x, CellValue::Garbage);
+        }
+        let queue = [Tet::I, Tet::T, Tet::S, Tet::Z];
+        let setup = GameSetup::new(board, &queue, Some(Tet::L));
+        assert_eq!(setup.queue(), queue.to_vec());
+        assert_eq!(setup.board(), board);
+
+        let mut state = GameState::from_setup(&setup, &[6; 32], 0, Default::default());
+        assert_eq!(state.current_pcs.unwrap().tet, Tet::I);
+        assert_eq!(state.hold_pcs.unwrap().tet, Tet::L);
+        assert_eq!(state.get_next_pcs()[..3], [Tet::T, Tet::S, Tet::Z]);
+
+        let mut segments = vec![state.last_segment];
+        for (i, action) in
+            [TetAction::RotateRight, TetAction::Hold, TetAction::HardDrop]
+                .into_iter()
+                .enumerate()
+        {
+            state = state.try_action(action, i as i64).unwrap();
+            segments.push(state.last_segment);
+        }
+        assert_eq!(segments_to_states(&segments).last(), Some(&state));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn randomizer_starts_a_bag_after_the_queue() {
+        let setup = GameSetup::new(BoardMatrix::empty(), &[Tet::O; 3], None);
+        let state = GameState::from_setup(&setup, &[7; 32], 0, Default::default());
+        let next = &state.next_pcs_bags[..state.next_pcs_len as usize];
+        assert_eq!(next[..2], [Tet::O, Tet::O]);
+        for tet in Tet::all() {
+            assert!(next[2..9].contains(&tet), "{tet:?} missing from the bag");
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use super::{
+    game_state::CurrentPcsInfo,
+    matrix::{BoardMatrix, CellValue},
+    tetpcs::Tet,
+};
+
+/// How a rotation counts when the piece locks without moving again.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SpinKind {
+    None,
+    Mini,
+    Full,
+}
+
+/// Spin check run right after a successful rotation; `kick` is the (x, y)
+/// offset the rotation system used to fit the piece.
+pub fn detect_spin(
+    board: &BoardMatrix,
+    pcs: &CurrentPcsInfo,
+    kick: (i8, i8),
+    all_spin: bool,
+) -> SpinKind {
+    if pcs.tet == Tet::T {
+        return detect_t_spin(board, pcs, kick);
+    }
+    if all_spin && is_immobile(board, pcs) {
+        return SpinKind::Mini;
+    }
+    SpinKind::None
+}
+
+/// Guideline 3-corner rule: 3 of the 4 corners around the T center must
+/// be blocked (walls and floor count). If both corners on the side the T
+/// points to are blocked it is a full T-spin, otherwise a mini, unless the
+/// piece got there with the long (1, 2) kick used by TST and fin setups.
+fn detect_t_spin(
+    board: &BoardMatrix,
+    pcs: &CurrentPcsInfo,
+    kick: (i8, i8),
+) -> SpinKind {
+    let shape = pcs.tet.shape(pcs.rot_system, pcs.rs);
+    let filled = |x: i8, y: i8| {
+        y >= 0
+            && (y as usize) < shape.len()
+            && x >= 0
+            && (x as usize) < shape[y as usize].len()
+            && shape[y as usize][x as usize]
+    };
+    // the T center is the only cell with 3 neighbours
+    let mut center = None;
+    for y in 0..shape.len() as i8 {
+        for x in 0..shape[0].len() as i8 {
+            let n = [(0, 1), (1, 0), (0, -1), (-1, 0)]
+                .iter()
+                .filter(|(dx, dy)| filled(x + dx, y + dy))
+                .count();
+            if filled(x, y) && n == 3 {
+                center = Some((x, y));
+            }
+        }
+    }
+    let Some((cx, cy)) = center else {
+        return SpinKind::None;
+    };
+    // the T points away from its missing neighbour
+    let Some((fx, fy)) = [(0, 1), (1, 0), (0, -1), (-1, 0)]
+        .into_iter()
+        .find(|(dx, dy)| filled(cx + dx, cy + dy) && !filled(cx - dx, cy - dy))
+    else {
+        return SpinKind::None;
+    };
+
+    let (py, px) = pcs.pos;
+    let blocked = |dx: i8, dy: i8| {
+        !matches!(
+            board.get_cell(py + cy + dy, px + cx + dx),
+            Some(CellValue::Empty) | Some(CellValue::Ghost)
+        )
+    };
+    let corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+    let blocked_count = corners.iter().filter(|(dx, dy)| blocked(*dx, *dy)).count();
+    if blocked_count < 3 {
+        return SpinKind::None;
+    }
+    let front_blocked = corners
+        .iter()
+        .filter(|(dx, dy)| dx * fx + dy * fy > 0)
+        .all(|(dx, dy)| blocked(*dx, *dy));
+    let long_kick = kick.0.abs() == 1 && kick.1.abs() == 2;
+    if front_blocked || long_kick {
+        SpinKind::Full
+    } else {
+        SpinKind::Mini
+    }
+}
+
+/// All-spin rule: the piece can not move left, right, up or down.
+fn is_immobile(board: &BoardMatrix, pcs: &CurrentPcsInfo) -> bool {
+    let mut board = *board;
+    if board.delete_piece(pcs).is_err() {
+        return false;
+    }
+    [(0, 1), (0, -1), (1, 0), (-1, 0)].iter().all(|(dy, dx)| {
+        let mut moved = *pcs;
+        moved.pos.0 += dy;
+        moved.pos.1 += dx;
+        let mut board = board;
+        board.spawn_piece(&moved).is_err()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{GameRules, GameState, RotState, TetAction};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    /// Board from text rows written top to bottom, the last one is y = 0;
+    /// the current piece is replaced by `tet` at `pos`.
+    fn setup(
+        rules: GameRules,
+        rows: &[&str],
+        tet: Tet,
+        pos: (i8, i8),
+        rs: RotState,
+    ) -> GameState {
+        let mut state = GameState::new_with_rules(&[0; 32], 0, rules);
+        state.main_board = BoardMatrix::empty();
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    state
+                        .main_board
+                        .set_cell(y as i8, x as i8, CellValue::Garbage);
+                }
+            }
+        }
+        let mut pcs = state.current_pcs.unwrap();
+        pcs.tet = tet;
+        pcs.pos = pos;
+        pcs.rs = rs;
+        state.main_board.spawn_piece(&pcs).unwrap();
+        state.current_pcs = Some(pcs);
+        state
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn t_spin_double() {
+        let rows = [
+            "...#......", //
+            "###...####",
+            "####.#####",
+        ];
+        let state = setup(GameRules::default(), &rows, Tet::T, (0, 3), RotState::R1);
+        let rotated = state.try_action(TetAction::RotateRight, 1).unwrap();
+        assert!(rotated.is_t_spin);
+        assert!(!rotated.is_t_mini_spin);
+        let dropped = rotated.try_action(TetAction::HardDrop, 2).unwrap();
+        assert_eq!(dropped.total_lines, 2);
+        assert_eq!(dropped.total_garbage_sent, 4);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn t_spin_triple() {
+        let rows = [
+            "....#.....", //
+            "##...#####",
+            "####.#####",
+            "###..#####",
+            "####.#####",
+        ];
+        let state = setup(GameRules::default(), &rows, Tet::T, (2, 2), RotState::R0);
+        let state = state.try_action(TetAction::RotateLeft, 1).unwrap();
+        assert!(state.is_t_spin);
+        assert_eq!(state.current_pcs.unwrap().pos, (0, 3));
+        let state = state.try_action(TetAction::HardDrop, 2).unwrap();
+        assert_eq!(state.total_lines, 3);
+        assert_eq!(state.total_garbage_sent, 6);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn stsd_is_upgraded_by_the_long_kick() {
+        let rows = [
+            "....#.....", //
+            "..........",
+            "####.#####",
+            "####..####",
+            "####..####",
+        ];
+        let state = setup(GameRules::default(), &rows, Tet::T, (2, 4), RotState::R0);
+        let state = state.try_action(TetAction::RotateRight, 1).unwrap();
+        assert_eq!(state.current_pcs.unwrap().pos, (0, 3));
+        assert!(state.is_t_spin);
+        assert!(!state.is_t_mini_spin);
+        let state = state.try_action(TetAction::HardDrop, 2).unwrap();
+        assert_eq!(state.total_lines, 2);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn t_spin_mini() {
+        let rows = [
+            "..........", //
+            "...#######",
+            ".#########",
+        ];
+        let state = setup(GameRules::default(), &rows, Tet::T, (0, 0), RotState::R0);
+        let state = state.try_action(TetAction::RotateRight, 1).unwrap();
+        assert!(!state.is_t_spin);
+        assert!(state.is_t_mini_spin);
+        let state = state.try_action(TetAction::HardDrop, 2).unwrap();
+        assert_eq!(state.total_lines, 1);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn moving_after_rotation_cancels_the_spin() {
+        let rows = [
+            "...#.#....", //
+            "###....###",
+            "####..####",
+        ];
+        let state = setup(GameRules::default(), &rows, Tet::T, (0, 3), RotState::R1);
+        let state = state.try_action(TetAction::RotateRight, 1).unwrap();
+        assert!(state.is_t_mini_spin);
+        let state = state.try_action(TetAction::MoveRight, 2).unwrap();
+        assert!(!state.is_t_spin && !state.is_t_mini_spin);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn kicked_non_t_pieces_only_spin_with_all_spin() {
+        let rows = [
+            "#.........", //
+            "..........",
+            "#.########",
+            "#..#######",
+            "##.#######",
+        ];
+        for all_spin in [false, true] {
+            let rules = GameRules {
+                all_spin,
+                ..Default::default()
+            };
+            let state = setup(rules, &rows, Tet::S, (2, 0), RotState::R0);
+            let state = state.try_action(TetAction::RotateRight, 1).unwrap();
+            assert!(!state.is_t_spin);
+            assert_eq!(state.is_t_mini_spin, all_spin);
+        }
+    }
+}
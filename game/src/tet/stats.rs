@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    finesse::FinesseTracker, scoring::LineClear, spin::SpinKind, tetpcs::TetAction,
+};
+
+/// Per-game counters, updated by `GameState` as the game is played. Replays
+/// re-simulate the game, so they get the same numbers for every state.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct GameStats {
+    pub pieces: u32,
+    /// player actions, gravity does not count
+    pub keys: u32,
+    /// garbage sent, including the lines used to cancel incoming garbage
+    pub attack: u32,
+    /// cleared lines that had garbage in them
+    pub garbage_cleared: u32,
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub quads: u32,
+    /// full T-spins, with or without lines
+    pub t_spins: u32,
+    /// mini T-spins and all-spins
+    pub mini_spins: u32,
+    pub perfect_clears: u32,
+    pub max_combo: u16,
+    /// current back-to-back chain, 0 after the first difficult clear
+    pub b2b_chain: u16,
+    pub max_b2b: u16,
+    /// time from the start to the last action
+    pub time_ms: i64,
+    /// placed pieces that used more keys than needed
+    pub finesse_faults: u32,
+    /// keys used above the minimum, over all finesse faults
+    pub extra_keys: u32,
+}
+
+impl GameStats {
+    pub fn on_action(&mut self, action: TetAction, time_ms: i64) {
+        if !matches!(action, TetAction::AutoSoftDrop | TetAction::Nothing) {
+            self.keys += 1;
+        }
+        self.time_ms = time_ms;
+    }
+
+    pub fn on_finesse(&mut self, finesse: &FinesseTracker) {
+        self.finesse_faults = finesse.faults;
+        self.extra_keys = finesse.extra_keys;
+    }
+
+    pub fn on_lock(&mut self) {
+        self.pieces += 1;
+    }
+
+    pub fn on_clear(&mut self, clear: &LineClear, attack: u16, garbage_cleared: u16) {
+        self.attack += attack as u32;
+        self.garbage_cleared += garbage_cleared as u32;
+        match clear.spin {
+            SpinKind::Full => self.t_spins += 1,
+            SpinKind::Mini => self.mini_spins += 1,
+            SpinKind::None => {}
+        }
+        if clear.lines == 0 {
+            return;
+        }
+        match clear.lines {
+            1 => self.singles += 1,
+            2 => self.doubles += 1,
+            3 => self.triples += 1,
+            _ => self.quads += 1,
+        }
+        if clear.perfect_clear {
+            self.perfect_clears += 1;
+        }
+        self.max_combo = self.max_combo.max(clear.combo.max(0) as u16);
+        if clear.b2b {
+            self.b2b_chain += 1;
+            self.max_b2b = self.max_b2b.max(self.b2b_chain);
+        } else {
+            self.b2b_chain = 0;
+        }
+    }
+
+    fn seconds(&self) -> f64 {
+        (self.time_ms as f64 / 1000.0).max(0.001)
+    }
+
+    /// Pieces per second.
+    pub fn pps(&self) -> f64 {
+        self.pieces as f64 / self.seconds()
+    }
+
+    /// Attack per minute.
+    pub fn apm(&self) -> f64 {
+        self.attack as f64 * 60.0 / self.seconds()
+    }
+
+    /// Keys per piece.
+    pub fn kpp(&self) -> f64 {
+        if self.pieces == 0 {
+            return 0.0;
+        }
+        self.keys as f64 / self.pieces as f64
+    }
+
+    /// Versus score: attack plus dug garbage per second, times 100.
+    pub fn vs_score(&self) -> f64 {
+        (self.attack + self.garbage_cleared) as f64 * 100.0 / self.seconds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{segments_to_states, GameState, Tet};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn rates() {
+        let stats = GameStats {
+            pieces: 20,
+            keys: 50,
+            attack: 10,
+            garbage_cleared: 5,
+            time_ms: 10_000,
+            ..Default::default()
+        };
+        assert_eq!(stats.pps(), 2.0);
+        assert_eq!(stats.apm(), 60.0);
+        assert_eq!(stats.kpp(), 2.5);
+        assert_eq!(stats.vs_score(), 150.0);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn stats_follow_the_game_and_its_replay() {
+        let mut state = GameState::new(&[2; 32], 0);
+        let mut segments = vec![state.last_segment];
+        for i in 0..30 {
+            let action = [TetAction::MoveLeft, TetAction::MoveRight][i % 2];
+            for action in [action, TetAction::RotateLeft, TetAction::HardDrop] {
+                if let Ok(s) = state.try_action(action, i as i64 * 100) {
+                    state = s;
+                    segments.push(state.last_segment);
+                }
+            }
+            if state.game_over() {
+                break;
+            }
+        }
+        assert!(state.stats.pieces > 0);
+        assert_eq!(state.stats.keys, state.total_moves as u32);
+        let replayed = segments_to_states(&segments);
+        assert_eq!(replayed.last().unwrap().stats, state.stats);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn finesse_faults_add_up() {
+        let mut state = GameState::new(&[0; 32], 0);
+        state.set_next_pcs(vec![Tet::O; 14]);
+        // taps instead of a held key: 4 keys for a 1 key placement
+        for piece in 0..2 {
+            let t = piece * 10_000;
+            for i in 0..4 {
+                state = state.try_action(TetAction::MoveLeft, t + i * 300).unwrap();
+            }
+            assert_eq!(state.stats.finesse_faults, piece as u32);
+            state = state.try_action(TetAction::HardDrop, t + 1_000).unwrap();
+        }
+        assert_eq!(state.stats.finesse_faults, 2);
+        assert_eq!(state.stats.extra_keys, 6);
+
+        // held to the wall, no new fault
+        state = state.try_action(TetAction::MoveRight, 30_000).unwrap();
+        state = state
+            .try_action(TetAction::MoveRightToWall, 30_140)
+            .unwrap();
+        state = state.try_action(TetAction::HardDrop, 30_200).unwrap();
+        assert_eq!(state.stats.finesse_faults, 2);
+    }
+}
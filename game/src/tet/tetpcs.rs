@@ -1,4 +1,5 @@
 use super::rot::{RotState, Shape};
+use super::rot_system::RotationSystemKind;
 use once_cell::sync::Lazy;
 use rand::{seq::IndexedRandom, Rng};
 use serde::{Deserialize, Serialize};
@@ -16,32 +17,23 @@ pub enum Tet {
     O,
 }
 
-pub static ALL_SHAPES: Lazy<std::collections::HashMap<(RotState, Tet), Shape>> =
-    Lazy::new(|| {
-        let mut h = std::collections::HashMap::<_, _>::new();
+pub static ALL_SHAPES: Lazy<
+    std::collections::HashMap<(RotationSystemKind, RotState, Tet), Shape>,
+> = Lazy::new(|| {
+    let mut h = std::collections::HashMap::<_, _>::new();
+    for k in RotationSystemKind::all() {
         for t in Tet::all() {
             for r in [RotState::R0, RotState::R1, RotState::R2, RotState::R3] {
-                let key = (r, t);
-                let val = t.make_shape(r);
+                let key = (k, r, t);
+                let val = k.get().shape(t, r);
                 h.insert(key, val);
             }
         }
-        h
-    });
-
-pub const SPAWN_POS: (i8, i8) = (18, 3);
+    }
+    h
+});
 
 impl Tet {
-    #[inline(always)]
-    pub fn spawn_pos(&self) -> (i8, i8) {
-        const O_SPAWN_POS: (i8, i8) = (SPAWN_POS.0 + 1, SPAWN_POS.1 + 1);
-        const I_SPAWN_POS: (i8, i8) = (SPAWN_POS.0 - 1, SPAWN_POS.1);
-        match *self {
-            Self::I => I_SPAWN_POS,
-            Self::O => O_SPAWN_POS,
-            _ => SPAWN_POS,
-        }
-    }
     pub fn name(&self) -> &str {
         match *self {
             Self::I => "I",
@@ -54,73 +46,22 @@ impl Tet {
         }
     }
 
+    pub fn from_char(c: char) -> Option<Self> {
+        Self::all().into_iter().find(|t| t.name().starts_with(c))
+    }
+
     #[inline(always)]
-    pub fn shape(&self, rot_state: super::rot::RotState) -> Shape {
+    pub fn shape(
+        &self,
+        rot_system: RotationSystemKind,
+        rot_state: super::rot::RotState,
+    ) -> Shape {
         ALL_SHAPES
-            .get(&(rot_state, *self))
+            .get(&(rot_system, rot_state, *self))
             .expect("rot shape combo not found; lazy not initialized.")
             .to_owned()
     }
 
-    fn make_shape(&self, rot_state: super::rot::RotState) -> Shape {
-        let mut sh = self.make_orig_shape();
-        match rot_state {
-            super::rot::RotState::R0 => {}
-
-            super::rot::RotState::R1 => {
-                sh = super::rot::rotate_shape(sh, super::rot::RotDirection::Right);
-            }
-
-            super::rot::RotState::R2 => {
-                sh = super::rot::rotate_shape(sh, super::rot::RotDirection::Right);
-                sh = super::rot::rotate_shape(sh, super::rot::RotDirection::Right);
-            }
-
-            super::rot::RotState::R3 => {
-                sh = super::rot::rotate_shape(sh, super::rot::RotDirection::Right);
-                sh = super::rot::rotate_shape(sh, super::rot::RotDirection::Right);
-                sh = super::rot::rotate_shape(sh, super::rot::RotDirection::Right);
-            }
-        }
-        sh
-    }
-
-    fn make_orig_shape(&self) -> Shape {
-        match *self {
-            Self::I => vec![
-                vec![false, false, false, false],
-                vec![false, false, false, false],
-                vec![true, true, true, true],
-                vec![false, false, false, false],
-            ],
-            Self::L => vec![
-                vec![false, false, false],
-                vec![true, true, true],
-                vec![false, false, true],
-            ],
-            Self::J => vec![
-                vec![false, false, false],
-                vec![true, true, true],
-                vec![true, false, false],
-            ],
-            Self::T => vec![
-                vec![false, false, false],
-                vec![true, true, true],
-                vec![false, true, false],
-            ],
-            Self::S => vec![
-                vec![false, false, false],
-                vec![true, true, false],
-                vec![false, true, true],
-            ],
-            Self::Z => vec![
-                vec![false, false, false],
-                vec![false, true, true],
-                vec![true, true, false],
-            ],
-            Self::O => vec![vec![true, true], vec![true, true]],
-        }
-    }
     pub fn random() -> Self {
         use rand::rng;
         let choices = Self::all();
@@ -145,7 +86,7 @@ impl Tet {
     Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord,
 )]
 pub enum TetAction {
-    // 3bit (8 acctions)
+    // 4bit (10 acctions)
     HardDrop,
     UserSoftDrop,
     MoveLeft,
@@ -155,6 +96,13 @@ pub enum TetAction {
     RotateRight,
     AutoSoftDrop,
     Nothing,
+    // new actions go at the end, so replays keep their encoding
+    Rotate180,
+    /// shift as far as the piece goes, for an auto-repeat rate of zero
+    MoveLeftToWall,
+    MoveRightToWall,
+    /// soft drop to the floor without locking
+    SoftDropToFloor,
 }
 
 impl TetAction {
@@ -170,6 +118,10 @@ impl TetAction {
             Self::Hold,
             Self::RotateLeft,
             Self::RotateRight,
+            Self::Rotate180,
+            Self::MoveLeftToWall,
+            Self::MoveRightToWall,
+            Self::SoftDropToFloor,
         ]
     }
     pub fn is_repeating(&self) -> bool {
@@ -191,6 +143,7 @@ impl TetAction {
                 Self::Hold,
                 Self::RotateLeft,
                 Self::RotateRight,
+                Self::Rotate180,
             ];
             let mut rng = rng();
             *choices.choose(&mut rng).unwrap()
@@ -0,0 +1,130 @@
+use super::game_state::{GameOverReason, GameReplaySegment, GameState};
+
+/// Re-simulates the step from `prev` to a state sent by a client and
+/// returns the simulated state, or an error when the two differ.
+///
+/// A new `Update` slice is played with `accept_replay_slice`. Without a new
+/// slice only received garbage may change, and the game may end for a
+/// reason decided outside of it (opponent knocked out, disconnect). A
+/// knockout always comes from a simulated slice.
+pub fn verify_next_state(
+    prev: &GameState,
+    received: &GameState,
+) -> anyhow::Result<GameState> {
+    if prev.game_over() && received != prev {
+        anyhow::bail!("game already over");
+    }
+    let mut expected = *prev;
+    // finesse is judged with the player's own DAS, a handling setting
+    expected.finesse.set_das_ms(received.finesse.das_ms());
+    if received.last_segment != prev.last_segment {
+        match &received.last_segment {
+            GameReplaySegment::Update(slice) => expected.accept_replay_slice(slice)?,
+            GameReplaySegment::GameOver(GameOverReason::Knockout) => {
+                anyhow::bail!("knockout without a replay slice")
+            }
+            GameReplaySegment::GameOver(reason) => expected.finish(*reason),
+            GameReplaySegment::Init(_) => {
+                anyhow::bail!("init segment in the middle of a game")
+            }
+        }
+    } else {
+        expected.apply_raw_received_garbage(received.garbage_recv);
+        match received.game_over_reason {
+            None => {}
+            Some(GameOverReason::Knockout) | Some(GameOverReason::Complete) => {
+                anyhow::bail!("game over without a replay segment")
+            }
+            Some(reason) => expected.game_over_reason = Some(reason),
+        }
+    }
+    // events are not sent over the network
+    expected.events = received.events;
+    if expected != *received {
+        anyhow::bail!(
+            "state does not match the re-simulated game at slice {}",
+            received.last_segment_idx
+        );
+    }
+    Ok(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{GameReplayInit, TetAction};
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn play(state: GameState, n: usize) -> Vec<GameState> {
+        let mut state = state;
+        let mut states = vec![state];
+        for i in 0..n {
+            let action = [
+                TetAction::MoveLeft,
+                TetAction::RotateLeft,
+                TetAction::HardDrop,
+            ][i % 3];
+            if let Ok(s) = state.try_action(action, 100 + i as i64 * 50) {
+                state = s;
+                states.push(state);
+            }
+        }
+        states
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn honest_game_verifies() {
+        let init = GameReplayInit::empty(&[3; 32], 0, Default::default());
+        let mut verified = GameState::from_init(&init);
+        let mut states = play(verified, 12);
+        let mut with_garbage = *states.last().unwrap();
+        with_garbage.apply_raw_received_garbage(4);
+        states.push(with_garbage);
+        states.extend(play(with_garbage, 6).into_iter().skip(1));
+        let mut won = *states.last().unwrap();
+        won.game_over_reason = Some(GameOverReason::Win);
+        states.push(won);
+
+        for s in &states {
+            verified = verify_next_state(&verified, s).unwrap();
+        }
+        assert_eq!(verified, won);
+        assert!(verify_next_state(&verified, &won).is_ok());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn tampered_states_are_rejected() {
+        let start = GameState::new(&[3; 32], 0);
+        let states = play(start, 3);
+
+        let mut more_score = states[3];
+        more_score.score += 1000;
+        assert!(verify_next_state(&states[2], &more_score).is_err());
+
+        // a slice that skips ahead
+        assert!(verify_next_state(&states[0], &states[2]).is_err());
+
+        let mut knockout = states[3];
+        knockout.game_over_reason = Some(GameOverReason::Knockout);
+        assert!(verify_next_state(&states[3], &knockout).is_err());
+
+        let mut complete = states[3];
+        complete.finish(GameOverReason::Complete);
+        assert!(verify_next_state(&states[3], &complete).is_ok());
+        assert!(verify_next_state(&complete, &states[3]).is_err());
+    }
+    #[test]
+    #[wasm_bindgen_test]
+    fn finesse_keeps_the_player_das() {
+        let server_start = GameState::new(&[3; 32], 0);
+        let mut client_start = server_start;
+        client_start.finesse.set_das_ms(40);
+        let mut verified = server_start;
+        for s in &play(client_start, 9)[1..] {
+            verified = verify_next_state(&verified, s).unwrap();
+        }
+        assert_eq!(verified.finesse.das_ms(), 40);
+    }
+}
@@ -1,3 +1,153 @@
+use std::{
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+
 pub fn get_timestamp_now_ms() -> i64 {
     chrono::offset::Utc::now().timestamp_millis()
 }
+
+/// Source of time for the input and state managers. Everything that reads
+/// the time or waits for a timer (DAS/ARR, auto soft drop, lock delay, solo
+/// mode timers) goes through one of these, so a game can run on a clock
+/// other than the wall clock.
+#[async_trait::async_trait]
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now_ms(&self) -> i64;
+    /// Resolves once `duration` has passed on this clock.
+    async fn sleep(&self, duration: Duration);
+}
+
+pub type SharedClock = Arc<dyn Clock + 'static>;
+
+pub fn real_clock() -> SharedClock {
+    Arc::new(RealClock)
+}
+
+/// Wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealClock;
+
+#[async_trait::async_trait]
+impl Clock for RealClock {
+    fn now_ms(&self) -> i64 {
+        get_timestamp_now_ms()
+    }
+    async fn sleep(&self, duration: Duration) {
+        n0_future::time::sleep(duration).await
+    }
+}
+
+/// Clock that only moves when told to. Sleeps wake up as soon as `advance`
+/// or `set` reaches their deadline.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicI64,
+    notify: Notify,
+}
+
+impl ManualClock {
+    pub fn new(start_ms: i64) -> Self {
+        Self {
+            now: AtomicI64::new(start_ms),
+            notify: Notify::new(),
+        }
+    }
+    pub fn advance(&self, duration: Duration) {
+        self.now
+            .fetch_add(duration.as_millis() as i64, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+    pub fn set(&self, now_ms: i64) {
+        self.now.store(now_ms, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+}
+
+#[async_trait::async_trait]
+impl Clock for ManualClock {
+    fn now_ms(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+    async fn sleep(&self, duration: Duration) {
+        let deadline = self.now_ms() + duration.as_millis() as i64;
+        loop {
+            let mut notified = std::pin::pin!(self.notify.notified());
+            // registered before the check, so an advance in between is seen
+            notified.as_mut().enable();
+            if self.now_ms() >= deadline {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Wall clock running `factor` times faster, starting at `start_ms`.
+#[derive(Debug, Clone, Copy)]
+pub struct AcceleratedClock {
+    start_ms: i64,
+    real_start_ms: i64,
+    factor: f64,
+}
+
+impl AcceleratedClock {
+    pub fn new(start_ms: i64, factor: f64) -> Self {
+        assert!(factor > 0.0, "clock factor must be positive");
+        Self {
+            start_ms,
+            real_start_ms: get_timestamp_now_ms(),
+            factor,
+        }
+    }
+    fn at_real_time(&self, real_ms: i64) -> i64 {
+        self.start_ms + ((real_ms - self.real_start_ms) as f64 * self.factor) as i64
+    }
+}
+
+#[async_trait::async_trait]
+impl Clock for AcceleratedClock {
+    fn now_ms(&self) -> i64 {
+        self.at_real_time(get_timestamp_now_ms())
+    }
+    async fn sleep(&self, duration: Duration) {
+        n0_future::time::sleep(duration.div_f64(self.factor)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn manual_clock_wakes_sleepers() {
+        let clock = ManualClock::new(1_000);
+        let mut sleep = Box::pin(clock.sleep(Duration::from_millis(100)));
+        assert!((&mut sleep).now_or_never().is_none());
+        clock.advance(Duration::from_millis(99));
+        assert!((&mut sleep).now_or_never().is_none());
+        clock.advance(Duration::from_millis(1));
+        assert!(sleep.now_or_never().is_some());
+        assert_eq!(clock.now_ms(), 1_100);
+
+        clock.set(5_000);
+        assert!(clock.sleep(Duration::ZERO).now_or_never().is_some());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn accelerated_clock_scales_time() {
+        let clock = AcceleratedClock::new(0, 10.0);
+        assert_eq!(clock.at_real_time(clock.real_start_ms + 50), 500);
+        assert_eq!(clock.at_real_time(clock.real_start_ms), 0);
+    }
+}
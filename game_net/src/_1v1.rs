@@ -9,12 +9,12 @@ use game::{
     input::{
         callback_manager::InputCallbackManagerRule, events::GameInputEvent,
     },
-    rule_manager::RuleManager,
+    rule_manager::{get_solo_mode_rule_with_clock, RuleManager},
     settings::GameSettings,
     state_manager::GameStateManager,
     tet::{GameOverReason, GameState},
 };
-use protocol::api::api_declarations::SendNewGameState;
+use protocol::api::api_declarations::SendNewGameStates;
 use protocol::api::client_api_manager::ClientApiManager;
 use protocol::chat::chat_controller::{
     ChatController, IChatController, IChatReceiver, IChatSender,
@@ -236,8 +236,12 @@ pub fn get_spectator_state_manager(
     cc: Game1v1MatchChatController,
 ) -> GameStateManager {
     tracing::info!("get_spectator_state_manager()");
-    let mut manager =
-        GameStateManager::new(&cc.match_info.seed, cc.match_info.time);
+    let mut manager = GameStateManager::new_with_setup(
+        &cc.match_info.seed,
+        cc.match_info.time,
+        cc.match_info.rules,
+        cc.match_info.setup(),
+    );
 
     let (state_tx, state_rx) = unbounded();
     let spectate_rule = Game1v1SpectatorRule(Mutex::new(state_rx));
@@ -263,16 +267,32 @@ pub fn get_1v1_player_state_manager(
     api: ClientApiManager,
 ) -> GameStateManager {
     tracing::info!("get_1v1_player_state_manager");
-    let mut game_state_manager =
-        GameStateManager::new(&cc.match_info.seed, cc.match_info.time);
+    let mut game_state_manager = GameStateManager::new_with_setup(
+        &cc.match_info.seed,
+        cc.match_info.time,
+        cc.match_info.rules,
+        cc.match_info.setup(),
+    );
+    if let Ok(settings) = settings.try_read() {
+        game_state_manager.set_input_settings(&settings.input);
+    }
 
-    let callback_manager = InputCallbackManagerRule::new(
+    let callback_manager = InputCallbackManagerRule::new_with_clock(
         player_input,
         game_state_manager.read_state_stream(),
         settings,
+        game_state_manager.clock(),
     );
     game_state_manager.add_rule("callback_manager", Arc::new(callback_manager));
 
+    // sprint, blitz, cheese and survival end (or push garbage) on their own
+    if let Some(solo_rule) = get_solo_mode_rule_with_clock(
+        &cc.match_info.type_,
+        game_state_manager.clock(),
+    ) {
+        game_state_manager.add_rule("solo_mode", solo_rule);
+    }
+
     // THIS LOOPP WILL SEND TO CHAT
     let g2 = game_state_manager.clone();
     let cc2 = cc.clone();
@@ -286,18 +306,35 @@ pub fn get_1v1_player_state_manager(
     });
 
     // THIS LOOP WILL SEND TO DB
+    // every state is sent, the server re-simulates the game step by step;
+    // they go in one request per locked piece and one at game over
     let g2 = game_state_manager.clone();
     let match_info = cc.match_info.clone();
     game_state_manager.add_loop(async move {
-        let stream = g2.read_state_stream();
-        pin_mut!(stream);
+        let mut stream = g2.read_every_state_stream().await;
+        let mut batch: Vec<GameState> = Vec::new();
         while let Some(s) = stream.next().await {
-            let arg = (match_info.clone(), s);
-            api.call_method::<SendNewGameState>(arg).await?;
+            if s.rules.practice {
+                // practice games are unranked
+                continue;
+            }
+            if batch.last().is_some_and(|b| b.current_id != s.current_id) {
+                let arg = (match_info.clone(), std::mem::take(&mut batch));
+                api.call_method::<SendNewGameStates>(arg).await?;
+            }
+            batch.push(s);
             let finish = s.game_over();
-            tracing::info!(
-                "game_state_manager SEND TO DB game_finish={finish}"
-            );
+            if finish {
+                let arg = (match_info.clone(), std::mem::take(&mut batch));
+                api.call_method::<SendNewGameStates>(arg).await?;
+                tracing::info!(
+                    "game_state_manager SEND TO DB game_finish={finish}"
+                );
+            }
+        }
+        if !batch.is_empty() {
+            let arg = (match_info.clone(), batch);
+            api.call_method::<SendNewGameStates>(arg).await?;
         }
         anyhow::Ok(())
     });
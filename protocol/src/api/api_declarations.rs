@@ -2,7 +2,7 @@
 
 use game::{
     api::game_match::{GameMatch, GameMatchType},
-    tet::GameState,
+    tet::{GameState, KeyframedReplay},
 };
 use serde::{Deserialize, Serialize};
 
@@ -20,7 +20,7 @@ declare_api_method!(
     Vec<GameState>
 );
 
-declare_api_method!(SendNewGameState, (GameMatch<NodeIdentity>, GameState), ());
+declare_api_method!(SendNewGameStates, (GameMatch<NodeIdentity>, Vec<GameState>), ());
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct MatchRow2 {
@@ -53,6 +53,13 @@ pub struct GameStateRow2 {
 
 declare_api_method!(GetGameStateRowsForMatch, MatchRow2, Vec<GameStateRow2>);
 
+// one keyframed replay per player, keyed by user id
+declare_api_method!(
+    GetKeyframedReplaysForMatch,
+    MatchRow2,
+    Vec<(String, KeyframedReplay)>
+);
+
 declare_api_method!(
     RunMultiplayerMatchmakerPhase1,
     GameMatchType,
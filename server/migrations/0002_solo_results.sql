@@ -0,0 +1,25 @@
+-- -------------------------------------------------------
+-- solo_results: finished 40 lines sprint and blitz games
+-- sprint is ranked by final_time_ms ascending, blitz by score descending
+-- -------------------------------------------------------
+CREATE TABLE IF NOT EXISTS solo_results (
+    game_type     VARCHAR(64)    NOT NULL,
+    user_id       VARCHAR(64)    NOT NULL,
+    start_time    BIGINT         NOT NULL,
+    game_seed     VARCHAR(64)    NOT NULL,
+    match_id      VARCHAR(64)    NOT NULL,
+    recv_time     BIGINT         NOT NULL,
+    score         BIGINT         NOT NULL,
+    total_lines   BIGINT         NOT NULL,
+    final_time_ms BIGINT         NOT NULL,
+    data_version  BIGINT         NOT NULL,
+    state_data    VARCHAR(2048)  NOT NULL,
+    id            BIGINT         NOT NULL AUTO_INCREMENT,
+    PRIMARY KEY (id),
+    INDEX idx_solo_results_time (game_type, final_time_ms),
+    INDEX idx_solo_results_score (game_type, score),
+    UNIQUE (game_type, user_id, start_time, game_seed, match_id)
+) ENGINE = InnoDB
+  CHARACTER SET utf8mb4
+  COLLATE utf8mb4_unicode_ci
+  COMMENT = 'results of finished solo mode games';
@@ -0,0 +1,38 @@
+-- -------------------------------------------------------
+-- game_stats: per-game statistics of finished games, all match types
+-- -------------------------------------------------------
+CREATE TABLE IF NOT EXISTS game_stats (
+    game_type       VARCHAR(64)  NOT NULL,
+    user_id         VARCHAR(64)  NOT NULL,
+    start_time      BIGINT       NOT NULL,
+    game_seed       VARCHAR(64)  NOT NULL,
+    match_id        VARCHAR(64)  NOT NULL,
+    recv_time       BIGINT       NOT NULL,
+    time_ms         BIGINT       NOT NULL,
+    pieces          BIGINT       NOT NULL,
+    keys_pressed    BIGINT       NOT NULL,
+    attack          BIGINT       NOT NULL,
+    garbage_cleared BIGINT       NOT NULL,
+    singles         BIGINT       NOT NULL,
+    doubles         BIGINT       NOT NULL,
+    triples         BIGINT       NOT NULL,
+    quads           BIGINT       NOT NULL,
+    t_spins         BIGINT       NOT NULL,
+    mini_spins      BIGINT       NOT NULL,
+    perfect_clears  BIGINT       NOT NULL,
+    max_combo       BIGINT       NOT NULL,
+    max_b2b         BIGINT       NOT NULL,
+    pps             DOUBLE       NOT NULL,
+    apm             DOUBLE       NOT NULL,
+    vs_score        DOUBLE       NOT NULL,
+    finesse_faults  BIGINT       NOT NULL,
+    extra_keys      BIGINT       NOT NULL,
+    data_version    BIGINT       NOT NULL,
+    id              BIGINT       NOT NULL AUTO_INCREMENT,
+    PRIMARY KEY (id),
+    INDEX idx_game_stats_user (user_id, game_type),
+    UNIQUE (game_type, user_id, start_time, game_seed, match_id)
+) ENGINE = InnoDB
+  CHARACTER SET utf8mb4
+  COLLATE utf8mb4_unicode_ci
+  COMMENT = 'statistics of finished games';
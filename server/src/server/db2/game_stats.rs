@@ -0,0 +1,61 @@
+use game::{api::game_match::GameMatch, tet::GameState, timestamp::get_timestamp_now_ms};
+use protocol::user_identity::NodeIdentity;
+
+use crate::server::db2::get_pool;
+use crate::server::db2::guest_login::serialize_base64;
+
+pub async fn db_save_game_stats(
+    _from: NodeIdentity,
+    _match: &GameMatch<NodeIdentity>,
+    game_state: &GameState,
+) -> anyhow::Result<()> {
+    let user_id = serialize_base64(_from.user_id().as_bytes())?;
+    let game_seed = serialize_base64(&_match.seed)?;
+    let game_type = format!("{:?}", _match.type_);
+    let match_id = _match.match_id.to_string();
+    let recv_time = get_timestamp_now_ms();
+    let stats = game_state.stats;
+
+    let pool = get_pool().await?;
+    sqlx::query!(
+        r#"
+INSERT IGNORE INTO game_stats (
+    game_type, user_id, start_time, game_seed, match_id, recv_time,
+    time_ms, pieces, keys_pressed, attack, garbage_cleared,
+    singles, doubles, triples, quads, t_spins, mini_spins, perfect_clears,
+    max_combo, max_b2b, pps, apm, vs_score, finesse_faults, extra_keys,
+    data_version
+)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        game_type,
+        user_id,
+        _match.time,
+        game_seed,
+        match_id,
+        recv_time,
+        stats.time_ms,
+        stats.pieces as i64,
+        stats.keys as i64,
+        stats.attack as i64,
+        stats.garbage_cleared as i64,
+        stats.singles as i64,
+        stats.doubles as i64,
+        stats.triples as i64,
+        stats.quads as i64,
+        stats.t_spins as i64,
+        stats.mini_spins as i64,
+        stats.perfect_clears as i64,
+        stats.max_combo as i64,
+        stats.max_b2b as i64,
+        stats.pps(),
+        stats.apm(),
+        stats.vs_score(),
+        stats.finesse_faults as i64,
+        stats.extra_keys as i64,
+        game_state.rules.version.number() as i64,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -1,11 +1,37 @@
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+
 use anyhow::Context;
-use game::api::game_match::GameMatch;
-use game::tet::GameState;
+use game::api::game_match::{GameMatch, LegacyGameMatch};
+use game::tet::{
+    GameState, KeyframedReplay, LegacyGameState, RulesVersion, REPLAY_KEYFRAME_INTERVAL,
+};
 use protocol::api::api_declarations::GameStateRow2;
 use protocol::{api::api_declarations::MatchRow2, user_identity::NodeIdentity};
 use crate::server::db2::get_pool;
 use crate::server::db2::guest_login::{serialize_base64, deserialize_base64};
 
+/// Rows are read with the layout of the rules version they were saved
+/// with; version 0 rows are from before versions were recorded.
+pub fn deserialize_game_state(data_version: i64, state_data: String) -> anyhow::Result<GameState> {
+    match RulesVersion::from_number(data_version.try_into()?)? {
+        RulesVersion::V0 => Ok(deserialize_base64::<LegacyGameState>(state_data)?.into()),
+        RulesVersion::V1 => deserialize_base64(state_data),
+    }
+}
+
+fn deserialize_match_info(
+    data_version: i64,
+    match_info: String,
+) -> anyhow::Result<GameMatch<NodeIdentity>> {
+    match RulesVersion::from_number(data_version.try_into()?)? {
+        RulesVersion::V0 => {
+            Ok(deserialize_base64::<LegacyGameMatch<NodeIdentity>>(match_info)?.into())
+        }
+        RulesVersion::V1 => deserialize_base64(match_info),
+    }
+}
+
 pub async fn db_get_list_matches(
     _from: NodeIdentity,
     _arg: (),
@@ -33,7 +59,7 @@ FROM matches
             game_seed: row.game_seed,
             match_id: row.match_id,
             data_version: row.data_version,
-            match_info: deserialize_base64(row.match_info).ok(),
+            match_info: deserialize_match_info(row.data_version, row.match_info).ok(),
         });
     }
 
@@ -67,7 +93,7 @@ WHERE match_id = ?
         game_seed: row.game_seed,
         match_id: row.match_id,
         data_version: row.data_version,
-        match_info: deserialize_base64(row.match_info).ok(),
+        match_info: deserialize_match_info(row.data_version, row.match_info).ok(),
     })
 }
 
@@ -102,12 +128,76 @@ ORDER BY user_id, recv_time
             recv_time: row.recv_time,
             data_version: row.data_version,
             last_action: row.last_action,
-            state_data: deserialize_base64(row.state_data).ok(),
+            state_data: deserialize_game_state(row.data_version, row.state_data).ok(),
         });
     }
     Ok(v)
 }
 
+type MatchReplays = Vec<(String, KeyframedReplay)>;
+
+/// Keyframed replays of finished matches, newest last. Building them
+/// re-simulates every game of the match, so they are kept until
+/// `KEYFRAMED_REPLAY_CACHE_LEN` newer matches push them out.
+static KEYFRAMED_REPLAYS: LazyLock<Mutex<VecDeque<(String, MatchReplays)>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::new()));
+const KEYFRAMED_REPLAY_CACHE_LEN: usize = 64;
+
+pub async fn db_get_keyframed_replays_for_match(
+    _from: NodeIdentity,
+    _arg: MatchRow2,
+) -> anyhow::Result<MatchReplays> {
+    let match_id = _arg.match_id.clone();
+    let cached = KEYFRAMED_REPLAYS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| *id == match_id)
+        .map(|(_, replays)| replays.clone());
+    if let Some(replays) = cached {
+        return Ok(replays);
+    }
+
+    let rows = db_get_game_states_for_match(_from, _arg).await?;
+
+    // rows come ordered by user, then by time
+    let mut per_user: Vec<(String, Vec<GameState>)> = vec![];
+    for row in rows {
+        let Some(state) = row.state_data else {
+            continue;
+        };
+        match per_user.last_mut() {
+            Some((user_id, states)) if *user_id == row.user_id => states.push(state),
+            _ => per_user.push((row.user_id, vec![state])),
+        }
+    }
+
+    let mut v = vec![];
+    let mut finished = !per_user.is_empty();
+    for (user_id, states) in per_user {
+        finished &= states.last().is_some_and(|s| s.game_over_reason.is_some());
+        match KeyframedReplay::from_saved_states(&states, REPLAY_KEYFRAME_INTERVAL) {
+            Ok(replay) => v.push((user_id, replay)),
+            Err(e) => {
+                tracing::warn!(
+                    "match {match_id}: skipping bad saved game for user {user_id}: {e:#}"
+                );
+                finished = false;
+            }
+        }
+    }
+
+    // games still being played get more states, and skipped ones are retried
+    if finished {
+        let mut cache = KEYFRAMED_REPLAYS.lock().unwrap();
+        if cache.len() >= KEYFRAMED_REPLAY_CACHE_LEN {
+            cache.pop_front();
+        }
+        cache.push_back((match_id, v.clone()));
+    }
+    Ok(v)
+}
+
 pub async fn get_last_game_states_for_match(
     _from: NodeIdentity,
     _match: GameMatch<NodeIdentity>,
@@ -117,16 +207,17 @@ pub async fn get_last_game_states_for_match(
     for user in _match.users.iter() {
         v.push(
             get_last_game_state_for_match_and_user(_match.clone(), *user)
-                .await?,
+                .await?
+                .context("no data found!")?,
         );
     }
     Ok(v)
 }
 
-async fn get_last_game_state_for_match_and_user(
+pub async fn get_last_game_state_for_match_and_user(
     _match: GameMatch<NodeIdentity>,
     user_id: NodeIdentity,
-) -> anyhow::Result<GameState> {
+) -> anyhow::Result<Option<GameState>> {
     let game_type = format!("{:?}", _match.type_);
     let start_time = _match.time;
     let game_seed = serialize_base64(&_match.seed)?;
@@ -135,7 +226,7 @@ async fn get_last_game_state_for_match_and_user(
     let pool = get_pool().await?;
     let row = sqlx::query!(
         r#"
-SELECT state_data FROM game_states
+SELECT data_version, state_data FROM game_states
 WHERE game_type = ?
   AND start_time = ?
   AND game_seed = ?
@@ -149,9 +240,11 @@ LIMIT 1
         user_id_str
     )
     .fetch_optional(pool)
-    .await?
-    .context("no data found!")?;
+    .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
 
-    let state: GameState = deserialize_base64(row.state_data)?;
-    Ok(state)
+    let state = deserialize_game_state(row.data_version, row.state_data)?;
+    Ok(Some(state))
 }
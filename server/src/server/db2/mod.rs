@@ -6,6 +6,8 @@ pub mod guest_login;
 pub mod user_friends;
 pub mod send_new_gamestate;
 pub mod send_new_match;
+pub mod solo_results;
+pub mod game_stats;
 pub mod get_replay_match_list2;
 pub mod get_user_profiles;
 
@@ -1,16 +1,50 @@
 use anyhow::Context;
 use game::api::game_match::GameMatchType;
+use game::rule_manager::solo_mode_is_complete;
 use game::{
-    api::game_match::GameMatch, tet::GameState, timestamp::get_timestamp_now_ms,
+    api::game_match::GameMatch,
+    tet::{verify_next_state, GameOverReason, GameState},
+    timestamp::get_timestamp_now_ms,
 };
 use protocol::user_identity::NodeIdentity;
 use crate::server::db2::get_pool;
+use crate::server::db2::get_replay_match_list2::{
+    db_get_detail_match, get_last_game_state_for_match_and_user,
+};
 use crate::server::db2::guest_login::serialize_base64;
 use crate::server::elo::compute_elo;
+use crate::server::db2::solo_results::db_save_solo_result;
+use crate::server::db2::game_stats::db_save_game_stats;
+
+/// States of one player in the order they were played, sent in batches
+/// of one piece and at the end of the game. Each one is re-simulated from
+/// the one before it, then saved.
+pub async fn db_send_new_gamestates(
+    _from: NodeIdentity,
+    (_match, game_states): (GameMatch<NodeIdentity>, Vec<GameState>),
+) -> anyhow::Result<()> {
+    if game_states.iter().any(|s| s.rules.practice) {
+        anyhow::bail!("practice games are not saved");
+    }
+    if game_states.is_empty() {
+        return Ok(());
+    }
+    let _match = get_stored_match(_from, &_match).await?;
+    let mut prev = get_last_game_state_for_match_and_user(_match.clone(), _from)
+        .await?
+        .unwrap_or_else(|| GameState::from_init(&_match.replay_init()));
+    for game_state in game_states {
+        let game_state = verify_received_gamestate(_from, &_match, &prev, &game_state).await?;
+        save_gamestate(_from, &_match, game_state).await?;
+        prev = game_state;
+    }
+    Ok(())
+}
 
-pub async fn db_send_new_gamestate(
+async fn save_gamestate(
     _from: NodeIdentity,
-    (_match, game_state): (GameMatch<NodeIdentity>, GameState),
+    _match: &GameMatch<NodeIdentity>,
+    game_state: GameState,
 ) -> anyhow::Result<()> {
     let user_id = serialize_base64(_from.user_id().as_bytes())?;
     let state_data = serialize_base64(&game_state)?;
@@ -37,7 +71,7 @@ VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         game_seed,
         recv_time,
         game_state.score as i64,
-        0i64,
+        game_state.rules.version.number() as i64,
         last_action,
         state_data,
         is_finished,
@@ -46,6 +80,23 @@ VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
     .execute(pool)
     .await?;
 
+    if is_finished {
+        db_save_game_stats(_from, &_match, &game_state).await?;
+    }
+
+    if _match.type_.is_solo_mode() {
+        // solo modes are ranked by their result, not by elo
+        let has_result = match game_state.game_over_reason {
+            Some(GameOverReason::Complete) => true,
+            // survival only ends by knockout
+            Some(GameOverReason::Knockout) => _match.type_ == GameMatchType::Survival,
+            _ => false,
+        };
+        if has_result {
+            db_save_solo_result(_from, &_match, &game_state).await?;
+        }
+        return Ok(());
+    }
 
     if is_finished{
         let match_id = _match.match_id.to_string();
@@ -90,7 +141,7 @@ VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,?,?,?)
         match_id,
         recv_time,
         game_state.score as i64,
-        0i64,
+        game_state.rules.version.number() as i64,
         last_action,
         state_data,
         is_finished,
@@ -145,6 +196,89 @@ async fn _get_elo(user_id: String, game_type: String) -> anyhow::Result<f64>  {
 // }
 
 
+/// The match as it was stored when it started. The copy sent along with
+/// every state is rejected when it differs, so its rules, seed, start time
+/// and users can not be swapped for the checks below.
+async fn get_stored_match(
+    _from: NodeIdentity,
+    sent: &GameMatch<NodeIdentity>,
+) -> anyhow::Result<GameMatch<NodeIdentity>> {
+    let row = db_get_detail_match(_from, sent.match_id.to_string())
+        .await
+        .context("match was not stored")?;
+    let stored = row.match_info.context("stored match can not be read")?;
+    if stored != *sent {
+        anyhow::bail!("match differs from the stored match");
+    }
+    Ok(stored)
+}
+
+/// How far ahead of ours a player's clock may run when a timed solo mode
+/// (Blitz) is reported complete.
+const SOLO_MODE_CLOCK_TOLERANCE_MS: i64 = 2_000;
+
+/// Re-simulates the received state from the one before it (the last one we
+/// stored for this player, or the match start) so scores and results can
+/// not be made up by the client. `_match` is the stored match, see
+/// `get_stored_match`.
+async fn verify_received_gamestate(
+    _from: NodeIdentity,
+    _match: &GameMatch<NodeIdentity>,
+    prev: &GameState,
+    game_state: &GameState,
+) -> anyhow::Result<GameState> {
+    if !_match.users.contains(&_from) {
+        anyhow::bail!("user is not in this match");
+    }
+    if game_state.rules != _match.rules {
+        anyhow::bail!("game rules do not match the match rules");
+    }
+    let verified = verify_next_state(prev, game_state)
+        .with_context(|| format!("rejected game state from {:?}", _from))?;
+
+    if prev.game_over() {
+        return Ok(verified);
+    }
+    match verified.game_over_reason {
+        Some(GameOverReason::Win) => {
+            if !opponent_lost(_from, _match).await? {
+                anyhow::bail!("win claimed but no opponent lost");
+            }
+        }
+        Some(GameOverReason::Complete) => {
+            // the solo mode must agree the game is complete, without waiting
+            let now = get_timestamp_now_ms();
+            if !solo_mode_is_complete(&_match.type_, *prev, now, SOLO_MODE_CLOCK_TOLERANCE_MS) {
+                anyhow::bail!("game complete claimed but the mode is not done");
+            }
+        }
+        _ => {}
+    }
+    Ok(verified)
+}
+
+/// Whether another player of the stored match lost.
+async fn opponent_lost(
+    our_node_id: NodeIdentity,
+    _match: &GameMatch<NodeIdentity>,
+) -> anyhow::Result<bool> {
+    for user in _match.users.iter().filter(|u| **u != our_node_id) {
+        let state = get_last_game_state_for_match_and_user(_match.clone(), *user).await?;
+        let lost = state.is_some_and(|s| {
+            matches!(
+                s.game_over_reason,
+                Some(GameOverReason::Knockout)
+                    | Some(GameOverReason::Disconnect)
+                    | Some(GameOverReason::Abandon)
+            )
+        });
+        if lost {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 async fn get_opponent_avg_elo(our_node_id: NodeIdentity, game_type: String, match_info: GameMatch<NodeIdentity>) -> f64 {
     
     let opponents = match_info.users.into_iter()
@@ -25,7 +25,7 @@ VALUES (?, ?, ?, ?, ?, ?)
         user_id,
         _match.time,
         game_seed,
-        0i64,
+        _match.rules.version.number() as i64,
         match_info
     )
     .execute(pool)
@@ -70,7 +70,7 @@ VALUES (?, ?, ?, ?, ?, ?, ?)
         user_ids_serialized,
         game_seed,
         _match.match_id.to_string(),
-        0i64,
+        _match.rules.version.number() as i64,
         match_info
     )
     .execute(pool)
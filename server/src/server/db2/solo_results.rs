@@ -0,0 +1,43 @@
+use game::{api::game_match::GameMatch, tet::GameState, timestamp::get_timestamp_now_ms};
+use protocol::user_identity::NodeIdentity;
+
+use crate::server::db2::get_pool;
+use crate::server::db2::guest_login::serialize_base64;
+
+pub async fn db_save_solo_result(
+    _from: NodeIdentity,
+    _match: &GameMatch<NodeIdentity>,
+    game_state: &GameState,
+) -> anyhow::Result<()> {
+    let user_id = serialize_base64(_from.user_id().as_bytes())?;
+    let state_data = serialize_base64(game_state)?;
+    let game_seed = serialize_base64(&_match.seed)?;
+    let game_type = format!("{:?}", _match.type_);
+    let match_id = _match.match_id.to_string();
+    let recv_time = get_timestamp_now_ms();
+
+    let pool = get_pool().await?;
+    sqlx::query!(
+        r#"
+INSERT IGNORE INTO solo_results (
+    game_type, user_id, start_time, game_seed, match_id, recv_time,
+    score, total_lines, final_time_ms, data_version, state_data
+)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        game_type,
+        user_id,
+        _match.time,
+        game_seed,
+        match_id,
+        recv_time,
+        game_state.score as i64,
+        game_state.total_lines as i64,
+        game_state.play_time_ms(),
+        game_state.rules.version.number() as i64,
+        state_data,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -58,6 +58,7 @@ pub async fn run_multiplayer_matchmaker_2(
         time: get_timestamp_now_ms(),
         users: match_identities.clone(),
         title: format!("{arg:?} - {match_identities:?}"),
+        rules: GameMatchType::get_match_rules(&Some(arg.clone())),
     };
     let proposed_match = serialize_base64(&proposed_match)?;
 